@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::path::Path;
+
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_json as json;
@@ -47,10 +50,22 @@ impl CargoRemote {
         &self,
         args: &[&str],
         backtrace: bool,
+    ) -> Result<(Vec<CargoMessage>, Vec<String>)> {
+        self.run_cargo_command_in(self.repository.root(), &HashMap::new(), args, backtrace)
+            .await
+    }
+
+    async fn run_cargo_command_in(
+        &self,
+        cwd: &Path,
+        environment: &HashMap<String, String>,
+        args: &[&str],
+        backtrace: bool,
     ) -> Result<(Vec<CargoMessage>, Vec<String>)> {
         let output = Command::new("cargo")
-            .current_dir(self.repository.root())
+            .current_dir(self.repository.root().join(cwd))
             .args(args)
+            .envs(environment)
             .env("RUST_BACKTRACE", if backtrace { "full" } else { "0" })
             .output()
             .await?;
@@ -101,4 +116,27 @@ impl CargoRemote {
         let (_, messages) = self.run_cargo_command(&args, backtrace).await?;
         Ok(messages)
     }
+
+    /// Runs `cargo` with the arguments of a runnable, in `cwd` (relative to the repository
+    /// root) and with `environment` set.
+    pub async fn test_with_args(
+        &self,
+        cargo_args: &[String],
+        executable_args: &[String],
+        cwd: Option<&Path>,
+        environment: &HashMap<String, String>,
+        backtrace: bool,
+    ) -> Result<Vec<String>> {
+        let mut args = cargo_args.iter().map(String::as_str).collect::<Vec<_>>();
+        args.push("--message-format=json");
+        if !executable_args.is_empty() {
+            args.push("--");
+            args.extend(executable_args.iter().map(String::as_str));
+        }
+        let cwd = cwd.unwrap_or(self.repository.root());
+        let (_, messages) = self
+            .run_cargo_command_in(cwd, environment, &args, backtrace)
+            .await?;
+        Ok(messages)
+    }
 }
@@ -7,7 +7,7 @@ use tokio::sync::{RwLock, RwLockWriteGuard};
 
 use crate::cargo_remote::CargoRemote;
 use crate::docs::{Docs, DocsNotification};
-use crate::lsp::LspNotification;
+use crate::lsp::{DiagnosticsStore, LspNotification, RustAnalyzerConfig, ServerLog};
 use crate::mcp::McpNotification;
 use crate::ui::{ProjectDescription, Settings};
 use crate::{
@@ -16,6 +16,10 @@ use crate::{
 };
 use anyhow::Result;
 use flume::Sender;
+use notify_debouncer_mini::{
+    DebounceEventResult, Debouncer, new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone)]
@@ -32,6 +36,8 @@ impl ContextNotification {
     pub fn notification_path(&self) -> PathBuf {
         match self {
             ContextNotification::Lsp(LspNotification::Indexing { project, .. }) => project.clone(),
+            ContextNotification::Lsp(LspNotification::Crashed { project, .. }) => project.clone(),
+            ContextNotification::Lsp(LspNotification::Restarted { project, .. }) => project.clone(),
             ContextNotification::Docs(DocsNotification::Indexing { project, .. }) => {
                 project.clone()
             }
@@ -51,6 +57,19 @@ impl ContextNotification {
                     if *is_indexing { "Started" } else { "Finished" }
                 )
             }
+            ContextNotification::Lsp(LspNotification::Crashed {
+                reason, restart_in, ..
+            }) => match restart_in {
+                Some(restart_in) => format!(
+                    "LSP Crashed: {}, restarting in {}s",
+                    reason,
+                    restart_in.as_secs()
+                ),
+                None => format!("LSP Crashed: {}, giving up", reason),
+            },
+            ContextNotification::Lsp(LspNotification::Restarted { restarts, .. }) => {
+                format!("LSP Restarted (attempt {})", restarts)
+            }
             ContextNotification::Docs(DocsNotification::Indexing { is_indexing, .. }) => {
                 format!(
                     "Docs Indexing: {}",
@@ -83,6 +102,8 @@ pub struct ProjectContext {
     pub lsp: RustAnalyzerLsp,
     pub docs: Docs,
     pub cargo_remote: CargoRemote,
+    pub diagnostics: DiagnosticsStore,
+    pub server_log: ServerLog,
     pub is_indexing_lsp: AtomicBool,
     pub is_indexing_docs: AtomicBool,
 }
@@ -95,6 +116,7 @@ pub struct Context {
     docs_sender: Sender<DocsNotification>,
     mcp_sender: Sender<McpNotification>,
     notifier: Sender<ContextNotification>,
+    config_watcher: Arc<std::sync::Mutex<Option<Debouncer<RecommendedWatcher>>>>,
 }
 
 impl Context {
@@ -124,12 +146,17 @@ impl Context {
                             project.is_indexing_docs.store(is_indexing, std::sync::atomic::Ordering::Relaxed);
                         }
                     }
-                    Ok(ref notification @ LspNotification::Indexing { ref project, is_indexing }) = lsp_receiver.recv_async() => {
+                    Ok(notification) = lsp_receiver.recv_async() => {
                         if let Err(e) = cloned_notifier.send(ContextNotification::Lsp(notification.clone())) {
                             tracing::error!("Failed to send LSP notification: {}", e);
                         }
+                        let (project, is_indexing) = match notification {
+                            LspNotification::Indexing { project, is_indexing } => (project, is_indexing),
+                            LspNotification::Crashed { project, .. } => (project, false),
+                            LspNotification::Restarted { project, .. } => (project, true),
+                        };
                         let mut projects: RwLockWriteGuard<'_, HashMap<PathBuf, Arc<ProjectContext>>> = cloned_projects.write().await;
-                        if let Some(project) = projects.get_mut(project) {
+                        if let Some(project) = projects.get_mut(&project) {
                             project.is_indexing_lsp.store(is_indexing, std::sync::atomic::Ordering::Relaxed);
                         }
                     }
@@ -147,6 +174,7 @@ impl Context {
             docs_sender,
             mcp_sender,
             notifier,
+            config_watcher: Arc::new(std::sync::Mutex::new(None)),
         }
     }
 
@@ -260,10 +288,10 @@ impl Context {
         let projects_map = self.projects.read().await;
         let projects_to_save: Vec<SerProject> = projects_map
             .values()
-            .map(|pc| &pc.project)
-            .map(|p| SerProject {
-                root: p.root().to_string_lossy().to_string(),
-                ignore_crates: p.ignore_crates().to_vec(),
+            .map(|pc| SerProject {
+                root: pc.project.root().to_string_lossy().to_string(),
+                ignore_crates: pc.project.ignore_crates().to_vec(),
+                rust_analyzer: pc.lsp.config(),
             })
             .collect();
 
@@ -319,6 +347,7 @@ impl Context {
             let project = Project {
                 root: PathBuf::from(&project.root),
                 ignore_crates: project.ignore_crates,
+                rust_analyzer: project.rust_analyzer,
             };
 
             if !project.root().exists() || !project.root().is_dir() {
@@ -330,7 +359,8 @@ impl Context {
             }
 
             match Project::new(project.root()) {
-                Ok(new_project) => {
+                Ok(mut new_project) => {
+                    new_project.rust_analyzer = project.rust_analyzer.clone();
                     if let Err(e) = self.add_project(new_project).await {
                         tracing::error!(
                             "Failed to add project {:?} from config: {}",
@@ -352,9 +382,74 @@ impl Context {
         Ok(())
     }
 
+    /// Watches the configuration file and sends changed rust-analyzer settings to the
+    /// projects' servers.
+    pub fn watch_config(&self) -> Result<()> {
+        let config_path = self.config_path();
+        let Some(config_dir) = config_path.parent().map(Path::to_path_buf) else {
+            return Ok(());
+        };
+        let context = self.clone();
+        let handle = tokio::runtime::Handle::current();
+        let mut debouncer = new_debouncer(
+            std::time::Duration::from_secs(1),
+            move |res: DebounceEventResult| match res {
+                Ok(events) => {
+                    if events.iter().any(|e| e.path == config_path) {
+                        let context = context.clone();
+                        handle.spawn(async move {
+                            if let Err(e) = context.reload_rust_analyzer_config().await {
+                                tracing::error!("Failed to reload rust-analyzer config: {}", e);
+                            }
+                        });
+                    }
+                }
+                Err(e) => tracing::error!("Error {:?}", e),
+            },
+        )?;
+        // The directory is watched because editors often replace the file when saving it
+        debouncer
+            .watcher()
+            .watch(&config_dir, RecursiveMode::NonRecursive)?;
+        *self.config_watcher.lock().unwrap() = Some(debouncer);
+        Ok(())
+    }
+
+    async fn reload_rust_analyzer_config(&self) -> Result<()> {
+        let config = self.read_config_file().await?;
+        for project in config.projects {
+            let Ok(root) = PathBuf::from(&project.root).canonicalize() else {
+                continue;
+            };
+            let Some(project_context) = self.get_project(&root).await else {
+                continue;
+            };
+            if let Err(e) = project_context
+                .lsp
+                .update_config(project.rust_analyzer)
+                .await
+            {
+                tracing::error!(
+                    "Failed to update rust-analyzer config for {:?}: {}",
+                    root,
+                    e
+                );
+            }
+        }
+        Ok(())
+    }
+
     pub async fn add_project(&self, project: Project) -> Result<()> {
         let root = project.root().clone();
-        let lsp = RustAnalyzerLsp::new(&project, self.lsp_sender.clone()).await?;
+        let diagnostics = DiagnosticsStore::default();
+        let server_log = ServerLog::default();
+        let lsp = RustAnalyzerLsp::new(
+            &project,
+            self.lsp_sender.clone(),
+            diagnostics.clone(),
+            server_log.clone(),
+        )
+        .await?;
         let docs = Docs::new(project.clone(), self.docs_sender.clone())?;
         docs.update_index().await?;
         let cargo_remote = CargoRemote::new(project.clone());
@@ -363,6 +458,8 @@ impl Context {
             lsp,
             docs,
             cargo_remote,
+            diagnostics,
+            server_log,
             is_indexing_lsp: AtomicBool::new(true),
             is_indexing_docs: AtomicBool::new(true),
         });
@@ -499,6 +596,8 @@ struct SerSettings {
 struct SerProject {
     root: String,
     ignore_crates: Vec<String>,
+    #[serde(default, skip_serializing_if = "RustAnalyzerConfig::is_default")]
+    rust_analyzer: RustAnalyzerConfig,
 }
 
 async fn project_descriptions(
@@ -518,6 +617,8 @@ async fn project_descriptions(
             is_indexing_lsp: project
                 .is_indexing_lsp
                 .load(std::sync::atomic::Ordering::Relaxed),
+            lsp_indexing_percentage: project.lsp.indexing_status().percentage,
+            server_log: project.server_log.clone(),
             is_indexing_docs: project
                 .is_indexing_docs
                 .load(std::sync::atomic::Ordering::Relaxed),
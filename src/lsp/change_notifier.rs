@@ -1,14 +1,19 @@
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::sync::{Arc, RwLock};
+use std::{path::Path, time::Duration};
 
 use anyhow::Result;
-use async_lsp::{LanguageServer, ServerSocket};
-use lsp_types::{DidChangeWatchedFilesParams, FileChangeType, FileEvent};
+use lsp_types::notification::{DidChangeWatchedFiles, DidSaveTextDocument};
+use lsp_types::{
+    DidChangeWatchedFilesParams, DidSaveTextDocumentParams, FileChangeType, FileEvent,
+    TextDocumentIdentifier,
+};
 use notify_debouncer_mini::{
     DebounceEventResult, DebouncedEvent, Debouncer, new_debouncer, notify::*,
 };
-use tokio::{runtime::Handle, sync::Mutex};
 use url::Url;
 
+use super::config::RustAnalyzerConfig;
+use super::server::Server;
 use crate::project::Project;
 
 #[derive(Debug)]
@@ -19,18 +24,17 @@ pub struct ChangeNotifier {
 
 impl ChangeNotifier {
     pub fn new(
-        server: Arc<Mutex<ServerSocket>>,
+        server: Server,
         project: &Project,
-        handle: Handle,
+        config: Arc<RwLock<RustAnalyzerConfig>>,
     ) -> Result<Self> {
-        let handle_clone = handle.clone();
         let target_path = project.root().join("target");
         let mut debouncer = new_debouncer(
             Duration::from_secs(2),
             move |res: DebounceEventResult| match res {
-                Ok(events) => events.iter().for_each(|e| {
-                    handle_event(e, server.clone(), handle_clone.clone(), target_path.clone())
-                }),
+                Ok(events) => events
+                    .iter()
+                    .for_each(|e| handle_event(e, &server, &target_path, &config)),
                 Err(e) => tracing::error!("Error {:?}", e),
             },
         )?;
@@ -44,11 +48,11 @@ impl ChangeNotifier {
 
 fn handle_event(
     event: &DebouncedEvent,
-    server: Arc<Mutex<ServerSocket>>,
-    handle: Handle,
-    target_path: PathBuf,
+    server: &Server,
+    target_path: &Path,
+    config: &RwLock<RustAnalyzerConfig>,
 ) {
-    if event.path.starts_with(&target_path) {
+    if event.path.starts_with(target_path) {
         return;
     }
     tracing::trace!("Event {:?} for {:?}", event.kind, event.path);
@@ -59,15 +63,22 @@ fn handle_event(
             return;
         }
     };
-    handle.spawn(async move {
-        match server
-            .lock()
-            .await
-            .did_change_watched_files(DidChangeWatchedFilesParams {
-                changes: vec![FileEvent::new(url, FileChangeType::CHANGED)],
-            }) {
-            Ok(_) => (),
-            Err(e) => tracing::error!("Failed to send DidChangeWatchedFiles notification: {:?}", e),
-        }
-    });
+    let is_rust_file = event.path.extension().is_some_and(|ext| ext == "rs");
+    match server.notify::<DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent::new(url.clone(), FileChangeType::CHANGED)],
+    }) {
+        Ok(_) => (),
+        Err(e) => tracing::error!("Failed to send DidChangeWatchedFiles notification: {:?}", e),
+    }
+    // rust-analyzer only re-runs `cargo check` (and refreshes its diagnostics) on save. Saving
+    // a document that was never opened is not covered by the protocol, so it is opt-in.
+    if is_rust_file
+        && config.read().unwrap().check_on_change
+        && let Err(e) = server.notify::<DidSaveTextDocument>(DidSaveTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri: url },
+            text: None,
+        })
+    {
+        tracing::error!("Failed to send DidSave notification: {:?}", e);
+    }
 }
@@ -1,26 +1,49 @@
+use std::collections::HashSet;
 use std::ops::ControlFlow;
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 
 use super::Stop;
+use super::config::RustAnalyzerConfig;
+use super::diagnostics::DiagnosticsStore;
+use super::ext::{ServerStatus, ServerStatusParams};
+use super::indexing::IndexingStatus;
+use super::server_log::ServerLog;
 use crate::lsp::LspNotification;
 use async_lsp::router::Router;
 use async_lsp::{LanguageClient, ResponseError};
+use futures::future::BoxFuture;
 use lsp_types::{
-    NumberOrString, ProgressParams, ProgressParamsValue, PublishDiagnosticsParams,
-    ShowMessageParams, WorkDoneProgress,
+    ConfigurationParams, LogMessageParams, MessageType, NumberOrString, ProgressParams,
+    ProgressParamsValue, PublishDiagnosticsParams, ShowMessageParams, WorkDoneProgress,
 };
+use tokio::sync::watch;
 
 
+// Prefixes of the progress tokens of loading the workspace, e.g. `rustAnalyzer/Building CrateGraph`
 const RA_INDEXING_TOKENS: &[&str] = &[
+    "rustAnalyzer/Fetching",
+    "rustAnalyzer/Loading proc-macros",
+    "rustAnalyzer/Roots Scanned",
+    "rustAnalyzer/Building",
     "rustAnalyzer/Indexing",
     "rustAnalyzer/cachePriming",
-    "rustAnalyzer/Building",
 ];
 
 pub struct ClientState {
     project: PathBuf,
     indexed_tx: Option<flume::Sender<()>>,
     notifier: flume::Sender<LspNotification>,
+    diagnostics: DiagnosticsStore,
+    config: Arc<RwLock<RustAnalyzerConfig>>,
+    indexing: watch::Sender<IndexingStatus>,
+    // rust-analyzer reports some indexing steps in parallel
+    indexing_tokens: HashSet<String>,
+    // From the latest server status, which rust-analyzer sends since it was asked to
+    quiescent: Option<bool>,
+    // Without a server status, indexing is done once the first reported steps ended
+    progress_ended: bool,
+    log: ServerLog,
 }
 
 impl LanguageClient for ClientState {
@@ -29,43 +52,87 @@ impl LanguageClient for ClientState {
 
     fn progress(&mut self, params: ProgressParams) -> Self::NotifyResult {
         tracing::trace!("{:?} {:?}", params.token, params.value);
-        let is_indexing =
-            matches!(params.token, NumberOrString::String(s) if RA_INDEXING_TOKENS.contains(&&*s));
-        let is_work_done = matches!(
-            params.value,
-            ProgressParamsValue::WorkDone(WorkDoneProgress::End(_))
-        );
-        if is_indexing && !is_work_done {
-            if let Err(e) = self.notifier.send(LspNotification::Indexing {
-                project: self.project.clone(),
-                is_indexing: true,
-            }) {
-                tracing::error!("Failed to send indexing notification: {}", e);
-            }
+        let NumberOrString::String(token) = params.token else {
+            return ControlFlow::Continue(());
+        };
+        if !RA_INDEXING_TOKENS
+            .iter()
+            .any(|prefix| token.starts_with(prefix))
+        {
+            return ControlFlow::Continue(());
         }
-        if is_indexing && is_work_done {
-            if let Err(e) = self.notifier.send(LspNotification::Indexing {
-                project: self.project.clone(),
-                is_indexing: false,
-            }) {
-                tracing::error!("Failed to send indexing notification: {}", e);
+        let ProgressParamsValue::WorkDone(progress) = params.value;
+        match progress {
+            WorkDoneProgress::Begin(begin) => {
+                self.indexing_tokens.insert(token);
+                self.indexing.send_replace(IndexingStatus {
+                    is_indexing: true,
+                    title: Some(begin.title),
+                    message: begin.message,
+                    percentage: begin.percentage,
+                });
             }
-
-            if let Some(tx) = &self.indexed_tx {
-                if let Err(e) = tx.try_send(()) {
-                    tracing::error!("Failed to send indexing completion signal: {}", e);
+            // Reports only carry what changed since the previous one
+            WorkDoneProgress::Report(report) => self.indexing.send_modify(|status| {
+                status.is_indexing = true;
+                if report.message.is_some() {
+                    status.message = report.message;
+                }
+                if report.percentage.is_some() {
+                    status.percentage = report.percentage;
+                }
+            }),
+            WorkDoneProgress::End(_) => {
+                self.indexing_tokens.remove(&token);
+                if self.indexing_tokens.is_empty() {
+                    self.progress_ended = true;
+                    self.indexing.send_replace(IndexingStatus::started());
                 }
             }
         }
+        self.update_indexing();
         ControlFlow::Continue(())
     }
 
-    fn publish_diagnostics(&mut self, _: PublishDiagnosticsParams) -> Self::NotifyResult {
+    fn publish_diagnostics(&mut self, params: PublishDiagnosticsParams) -> Self::NotifyResult {
+        match params.uri.to_file_path() {
+            Ok(path) => self
+                .diagnostics
+                .update(path, params.version, params.diagnostics),
+            Err(_) => tracing::error!("Invalid diagnostics URL: {}", params.uri),
+        }
         ControlFlow::Continue(())
     }
 
+    fn configuration(
+        &mut self,
+        params: ConfigurationParams,
+    ) -> BoxFuture<'static, Result<Vec<serde_json::Value>, Self::Error>> {
+        let settings = self.config.read().unwrap().settings();
+        let items = params
+            .items
+            .iter()
+            .map(|item| match item.section.as_deref() {
+                Some("rust-analyzer") => settings.clone(),
+                _ => serde_json::Value::Null,
+            })
+            .collect();
+        Box::pin(async move { Ok(items) })
+    }
+
     fn show_message(&mut self, params: ShowMessageParams) -> Self::NotifyResult {
-        tracing::debug!("Message {:?}: {}", params.typ, params.message);
+        match params.typ {
+            MessageType::ERROR => tracing::error!("rust-analyzer: {}", params.message),
+            MessageType::WARNING => tracing::warn!("rust-analyzer: {}", params.message),
+            _ => tracing::info!("rust-analyzer: {}", params.message),
+        }
+        self.log.push_message(params.typ, &params.message);
+        ControlFlow::Continue(())
+    }
+
+    fn log_message(&mut self, params: LogMessageParams) -> Self::NotifyResult {
+        tracing::debug!("rust-analyzer: {}", params.message);
+        self.log.push_message(params.typ, &params.message);
         ControlFlow::Continue(())
     }
 }
@@ -75,17 +142,136 @@ impl ClientState {
         indexed_tx: flume::Sender<()>,
         notifier: flume::Sender<LspNotification>,
         project: PathBuf,
+        diagnostics: DiagnosticsStore,
+        config: Arc<RwLock<RustAnalyzerConfig>>,
+        indexing: watch::Sender<IndexingStatus>,
+        log: ServerLog,
     ) -> Router<Self> {
         let mut router = Router::from_language_client(ClientState {
             indexed_tx: Some(indexed_tx),
             notifier,
             project,
+            diagnostics,
+            config,
+            indexing,
+            indexing_tokens: HashSet::new(),
+            quiescent: None,
+            progress_ended: false,
+            log,
         });
-        router.event(Self::on_stop);
+        router
+            .notification::<ServerStatus>(Self::server_status)
+            .event(Self::on_stop);
         router
     }
 
+    // Publishes whether rust-analyzer is indexing after the progress or server status changed
+    fn update_indexing(&mut self) {
+        let is_indexing =
+            !self.indexing_tokens.is_empty() || !self.quiescent.unwrap_or(self.progress_ended);
+        self.indexing
+            .send_modify(|status| status.is_indexing = is_indexing);
+        if let Err(e) = self.notifier.send(LspNotification::Indexing {
+            project: self.project.clone(),
+            is_indexing,
+        }) {
+            tracing::error!("Failed to send indexing notification: {}", e);
+        }
+        if !is_indexing
+            && let Some(tx) = &self.indexed_tx
+            && let Err(e) = tx.try_send(())
+        {
+            tracing::error!("Failed to send indexing completion signal: {}", e);
+        }
+    }
+
+    fn server_status(&mut self, params: ServerStatusParams) -> ControlFlow<async_lsp::Result<()>> {
+        tracing::trace!("Server status {:?}", params);
+        self.quiescent = Some(params.quiescent);
+        self.update_indexing();
+        ControlFlow::Continue(())
+    }
+
     pub fn on_stop(&mut self, _: Stop) -> ControlFlow<async_lsp::Result<()>> {
         ControlFlow::Break(Ok(()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{ProgressToken, WorkDoneProgressBegin, WorkDoneProgressEnd};
+
+    use super::*;
+
+    fn client_state() -> (ClientState, watch::Receiver<IndexingStatus>) {
+        let (indexing, status) = watch::channel(IndexingStatus::started());
+        let state = ClientState {
+            project: PathBuf::from("/project"),
+            indexed_tx: None,
+            notifier: flume::unbounded().0,
+            diagnostics: DiagnosticsStore::default(),
+            config: Arc::default(),
+            indexing,
+            indexing_tokens: HashSet::new(),
+            quiescent: None,
+            progress_ended: false,
+            log: ServerLog::default(),
+        };
+        (state, status)
+    }
+
+    fn progress(state: &mut ClientState, token: &str, progress: WorkDoneProgress) {
+        let _ = state.progress(ProgressParams {
+            token: ProgressToken::String(token.to_string()),
+            value: ProgressParamsValue::WorkDone(progress),
+        });
+    }
+
+    fn server_status(state: &mut ClientState, quiescent: bool) {
+        let _ = state.server_status(ServerStatusParams {
+            health: "ok".to_string(),
+            quiescent,
+            message: None,
+        });
+    }
+
+    #[test]
+    fn test_indexing_until_progress_ends() {
+        let (mut state, status) = client_state();
+        assert!(status.borrow().is_indexing);
+        progress(
+            &mut state,
+            "rustAnalyzer/Fetching",
+            WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: "Fetching".to_string(),
+                ..WorkDoneProgressBegin::default()
+            }),
+        );
+        assert_eq!(status.borrow().title.as_deref(), Some("Fetching"));
+        progress(
+            &mut state,
+            "rustAnalyzer/Fetching",
+            WorkDoneProgress::End(WorkDoneProgressEnd::default()),
+        );
+        assert!(!status.borrow().is_indexing);
+    }
+
+    #[test]
+    fn test_indexing_until_quiescent() {
+        let (mut state, status) = client_state();
+        server_status(&mut state, false);
+        progress(
+            &mut state,
+            "rustAnalyzer/Roots Scanned",
+            WorkDoneProgress::Begin(WorkDoneProgressBegin::default()),
+        );
+        progress(
+            &mut state,
+            "rustAnalyzer/Roots Scanned",
+            WorkDoneProgress::End(WorkDoneProgressEnd::default()),
+        );
+        assert!(status.borrow().is_indexing);
+        server_status(&mut state, true);
+        assert!(!status.borrow().is_indexing);
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+const DEFAULT_REQUEST_TIMEOUT: u64 = 60;
+
+/// The `rust_analyzer` table of a project in the configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RustAnalyzerConfig {
+    /// Defaults to `rust-analyzer` from the `PATH`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// rust-analyzer settings without the `rust-analyzer.` prefix, e.g. `check.command`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initialization_options: Option<Value>,
+    /// Saves every changed `.rs` file in rust-analyzer, although it was never opened there, so
+    /// that it re-runs `cargo check` for changes made outside of the tools
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub check_on_change: bool,
+    /// Seconds to wait for a response before the request is cancelled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_timeout: Option<u64>,
+    /// Timeouts of single requests by their method, e.g. `textDocument/references`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub request_timeouts: BTreeMap<String, u64>,
+}
+
+impl RustAnalyzerConfig {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn binary(&self) -> PathBuf {
+        self.binary
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("rust-analyzer"))
+    }
+
+    pub fn request_timeout(&self, method: &str) -> Duration {
+        let seconds = self
+            .request_timeouts
+            .get(method)
+            .copied()
+            .or(self.request_timeout)
+            .unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+        Duration::from_secs(seconds)
+    }
+
+    /// The settings rust-analyzer is started with, the configured options on top of the ones
+    /// the tools rely on.
+    pub fn settings(&self) -> Value {
+        let mut settings = json!({
+            "hover": {
+                "memoryLayout": {
+                    "niches": true
+                }
+            }
+        });
+        if let Some(options) = &self.initialization_options {
+            merge(&mut settings, options);
+        }
+        settings
+    }
+}
+
+fn merge(target: &mut Value, source: &Value) {
+    match (target, source) {
+        (Value::Object(target), Value::Object(source)) => {
+            for (key, value) in source {
+                match target.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, source) => *target = source.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings() {
+        let config: RustAnalyzerConfig = toml::from_str(
+            r#"
+            binary = "/opt/rust-analyzer"
+            check_on_change = true
+            request_timeout = 20
+
+            [request_timeouts]
+            "textDocument/references" = 120
+
+            [initialization_options]
+            cargo.features = ["serde"]
+            check.command = "clippy"
+            hover.memoryLayout.size = "hexadecimal"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.binary(), PathBuf::from("/opt/rust-analyzer"));
+        assert!(config.check_on_change);
+        assert_eq!(
+            config.request_timeout("textDocument/references"),
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            config.request_timeout("textDocument/hover"),
+            Duration::from_secs(20)
+        );
+        assert_eq!(
+            config.settings(),
+            json!({
+                "cargo": { "features": ["serde"] },
+                "check": { "command": "clippy" },
+                "hover": { "memoryLayout": { "niches": true, "size": "hexadecimal" } }
+            })
+        );
+        assert!(RustAnalyzerConfig::default().is_default());
+    }
+}
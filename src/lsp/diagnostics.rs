@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use lsp_types::Diagnostic;
+use tokio::sync::Notify;
+
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticsStore {
+    files: Arc<RwLock<HashMap<PathBuf, Vec<Diagnostic>>>>,
+    versions: Arc<RwLock<HashMap<PathBuf, i32>>>,
+    updated: Arc<Notify>,
+}
+
+impl DiagnosticsStore {
+    pub fn update(&self, path: PathBuf, version: Option<i32>, diagnostics: Vec<Diagnostic>) {
+        match version {
+            Some(version) => self.versions.write().unwrap().insert(path.clone(), version),
+            None => self.versions.write().unwrap().remove(&path),
+        };
+        let mut files = self.files.write().unwrap();
+        if diagnostics.is_empty() {
+            files.remove(&path);
+        } else {
+            files.insert(path, diagnostics);
+        }
+        drop(files);
+        self.updated.notify_waiters();
+    }
+
+    /// Forgets the diagnostics of a server that stopped, its successor publishes its own.
+    pub fn clear(&self) {
+        self.files.write().unwrap().clear();
+        self.versions.write().unwrap().clear();
+        self.updated.notify_waiters();
+    }
+
+    pub fn file(&self, path: &Path) -> Vec<Diagnostic> {
+        self.files
+            .read()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn all(&self) -> Vec<(PathBuf, Vec<Diagnostic>)> {
+        let mut files = self
+            .files
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(path, diagnostics)| (path.clone(), diagnostics.clone()))
+            .collect::<Vec<_>>();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        files
+    }
+
+    /// Waits until diagnostics for at least `version` of an open document were published.
+    pub async fn wait_for_version(&self, path: &Path, version: i32, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                let notified = self.updated.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+                let published = self.versions.read().unwrap().get(path).copied();
+                if published.is_some_and(|published| published >= version) {
+                    return;
+                }
+                notified.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use lsp_types::notification::Notification;
+use lsp_types::request::Request;
+use lsp_types::{
+    LocationLink, PartialResultParams, Position, Range, TextDocumentIdentifier,
+    TextDocumentPositionParams, WorkDoneProgressParams, WorkspaceEdit, WorkspaceSymbolResponse,
+};
+use serde::{Deserialize, Serialize};
+
+pub enum WorkspaceSymbol {}
+
+impl Request for WorkspaceSymbol {
+    type Params = WorkspaceSymbolParams;
+    type Result = Option<WorkspaceSymbolResponse>;
+    const METHOD: &'static str = "workspace/symbol";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceSymbolParams {
+    #[serde(flatten)]
+    pub partial_result_params: PartialResultParams,
+    #[serde(flatten)]
+    pub work_done_progress_params: WorkDoneProgressParams,
+    pub query: String,
+    pub search_scope: Option<WorkspaceSymbolSearchScope>,
+    pub search_kind: Option<WorkspaceSymbolSearchKind>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WorkspaceSymbolSearchScope {
+    Workspace,
+    WorkspaceAndDependencies,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WorkspaceSymbolSearchKind {
+    OnlyTypes,
+    AllSymbols,
+}
+
+pub enum ExpandMacro {}
+
+impl Request for ExpandMacro {
+    type Params = ExpandMacroParams;
+    type Result = Option<ExpandedMacro>;
+    const METHOD: &'static str = "rust-analyzer/expandMacro";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandMacroParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpandedMacro {
+    pub name: String,
+    pub expansion: String,
+}
+
+pub enum ViewSyntaxTree {}
+
+impl Request for ViewSyntaxTree {
+    type Params = ViewSyntaxTreeParams;
+    /// The tree as JSON, see `format_syntax_tree`
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/viewSyntaxTree";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewSyntaxTreeParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// Replaced by `ViewSyntaxTree` in rust-analyzer versions since 2025.
+pub enum SyntaxTree {}
+
+impl Request for SyntaxTree {
+    type Params = SyntaxTreeParams;
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/syntaxTree";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyntaxTreeParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Option<Range>,
+}
+
+pub enum ViewHir {}
+
+impl Request for ViewHir {
+    type Params = TextDocumentPositionParams;
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/viewHir";
+}
+
+pub enum ViewRecursiveMemoryLayout {}
+
+impl Request for ViewRecursiveMemoryLayout {
+    type Params = TextDocumentPositionParams;
+    type Result = Option<RecursiveMemoryLayout>;
+    const METHOD: &'static str = "rust-analyzer/viewRecursiveMemoryLayout";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecursiveMemoryLayout {
+    pub nodes: Vec<MemoryLayoutNode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryLayoutNode {
+    pub item_name: String,
+    pub typename: String,
+    pub size: u64,
+    pub offset: u64,
+    pub alignment: u64,
+    pub parent_idx: i64,
+    pub children_start: i64,
+    pub children_len: u64,
+}
+
+pub enum ViewCrateGraph {}
+
+impl Request for ViewCrateGraph {
+    type Params = ViewCrateGraphParams;
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/viewCrateGraph";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewCrateGraphParams {
+    pub full: bool,
+}
+
+pub enum RelatedTests {}
+
+impl Request for RelatedTests {
+    type Params = TextDocumentPositionParams;
+    type Result = Vec<TestInfo>;
+    const METHOD: &'static str = "rust-analyzer/relatedTests";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestInfo {
+    pub runnable: Runnable,
+}
+
+pub enum Runnables {}
+
+impl Request for Runnables {
+    type Params = RunnablesParams;
+    type Result = Vec<Runnable>;
+    const METHOD: &'static str = "experimental/runnables";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnablesParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Option<Position>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Runnable {
+    pub label: String,
+    pub location: Option<LocationLink>,
+    pub kind: String,
+    pub args: RunnableArgs,
+}
+
+// Only the fields of cargo runnables, shell runnables are listed but can not be run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnableArgs {
+    #[serde(default)]
+    pub cargo_args: Vec<String>,
+    #[serde(default)]
+    pub executable_args: Vec<String>,
+    // The package directory, older rust-analyzer versions leave it out
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    #[serde(default)]
+    pub environment: HashMap<String, String>,
+}
+
+pub enum Ssr {}
+
+impl Request for Ssr {
+    type Params = SsrParams;
+    type Result = WorkspaceEdit;
+    const METHOD: &'static str = "experimental/ssr";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SsrParams {
+    pub query: String,
+    pub parse_only: bool,
+    #[serde(flatten)]
+    pub position: TextDocumentPositionParams,
+    pub selections: Vec<Range>,
+}
+
+pub enum ServerStatus {}
+
+impl Notification for ServerStatus {
+    type Params = ServerStatusParams;
+    const METHOD: &'static str = "experimental/serverStatus";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatusParams {
+    /// `ok`, `warning` or `error`
+    pub health: String,
+    /// Whether rust-analyzer finished loading the workspace and has no pending work
+    pub quiescent: bool,
+    pub message: Option<String>,
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+
+/// How far rust-analyzer got with indexing the project, from its latest progress report.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexingStatus {
+    pub is_indexing: bool,
+    /// The step rust-analyzer is working on, e.g. `Indexing` or `Building build-artifacts`
+    pub title: Option<String>,
+    pub message: Option<String>,
+    pub percentage: Option<u32>,
+}
+
+impl IndexingStatus {
+    pub fn started() -> Self {
+        Self {
+            is_indexing: true,
+            ..Self::default()
+        }
+    }
+}
+
+impl fmt::Display for IndexingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_indexing {
+            return write!(f, "indexed");
+        }
+        write!(f, "indexing")?;
+        if let Some(percentage) = self.percentage {
+            write!(f, " {percentage}%")?;
+        }
+        match (&self.title, &self.message) {
+            (Some(title), Some(message)) => write!(f, " ({title}: {message})"),
+            (Some(step), None) | (None, Some(step)) => write!(f, " ({step})"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let status = IndexingStatus {
+            is_indexing: true,
+            title: Some("Indexing".to_string()),
+            message: Some("120/300 (serde)".to_string()),
+            percentage: Some(42),
+        };
+        assert_eq!(
+            status.to_string(),
+            "indexing 42% (Indexing: 120/300 (serde))"
+        );
+        assert_eq!(IndexingStatus::started().to_string(), "indexing");
+        assert_eq!(IndexingStatus::default().to_string(), "indexed");
+    }
+}
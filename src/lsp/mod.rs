@@ -1,16 +1,44 @@
 mod change_notifier;
 mod client_state;
+mod config;
+mod diagnostics;
+mod ext;
+mod indexing;
+mod overlays;
 mod rust_analyzer_lsp;
+mod server;
+mod server_log;
 mod utils;
+mod workspace_edit;
 
 pub(super) struct Stop;
 
 use std::path::PathBuf;
+use std::time::Duration;
 
+pub use config::RustAnalyzerConfig;
+pub use diagnostics::DiagnosticsStore;
+pub use ext::{MemoryLayoutNode, Runnable};
 pub use rust_analyzer_lsp::RustAnalyzerLsp;
+pub use server_log::ServerLog;
 pub use utils::*;
+pub use workspace_edit::*;
 
 #[derive(Debug, Clone)]
 pub enum LspNotification {
-    Indexing { project: PathBuf, is_indexing: bool },
+    Indexing {
+        project: PathBuf,
+        is_indexing: bool,
+    },
+    /// rust-analyzer stopped without being shut down. `restart_in` is `None` once the
+    /// supervisor gave up restarting it.
+    Crashed {
+        project: PathBuf,
+        reason: String,
+        restart_in: Option<Duration>,
+    },
+    Restarted {
+        project: PathBuf,
+        restarts: u32,
+    },
 }
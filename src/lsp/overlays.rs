@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use lsp_types::notification::{DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument};
+use lsp_types::{
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams, Range,
+    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem, Url,
+    VersionedTextDocumentIdentifier,
+};
+use tokio::sync::OwnedMutexGuard;
+
+use super::server::Server;
+use super::workspace_edit::offset_to_position;
+
+#[derive(Debug)]
+struct OverlayDocument {
+    version: i32,
+    text: String,
+}
+
+#[derive(Debug, Default)]
+struct Users {
+    lock: Arc<tokio::sync::Mutex<()>>,
+    // Callers waiting for `lock`, an open overlay is handed over to them instead of being closed
+    waiting: usize,
+}
+
+/// Documents whose content rust-analyzer sees in place of the file on disk. Only one user at a
+/// time can use a document, the others wait until it is done with it. An overlay that is still
+/// open when the next user gets its turn only receives the changes of its content.
+#[derive(Debug, Default)]
+pub struct Overlays {
+    documents: Mutex<HashMap<Url, OverlayDocument>>,
+    // Locked before `documents` where both are needed
+    users: Mutex<HashMap<Url, Users>>,
+    // Versions increase across reopened documents so that diagnostics published for an
+    // earlier overlay of the same file are never mistaken for the current one
+    next_version: AtomicI32,
+}
+
+/// An open overlay, which is closed when it is dropped, unless the next user of the document is
+/// already waiting for it.
+#[derive(Debug)]
+pub struct Overlay {
+    overlays: Arc<Overlays>,
+    server: Server,
+    uri: Url,
+    version: i32,
+    user: Option<OwnedMutexGuard<()>>,
+}
+
+impl Overlay {
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        // Released while `users` is locked, so that a waiter that gives up sees it released
+        let users = self.overlays.users.lock().unwrap();
+        if users.get(&self.uri).is_some_and(|users| users.waiting > 0) {
+            self.user.take();
+            return;
+        }
+        if let Err(e) = self.overlays.close(&self.server, &self.uri) {
+            tracing::error!("Failed to close overlay for {}: {:#}", self.uri, e);
+        }
+        self.user.take();
+    }
+}
+
+/// Keeps other users from opening an overlay of a document, which rust-analyzer sees as it is
+/// on disk until the lock is dropped.
+#[derive(Debug)]
+pub struct DocumentLock {
+    _user: OwnedMutexGuard<()>,
+}
+
+// Closes an overlay that was handed over to a caller which stopped waiting for it
+struct Waiting<'a> {
+    overlays: &'a Overlays,
+    server: &'a Server,
+    uri: &'a Url,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let mut users = self.overlays.users.lock().unwrap();
+        let Some(users) = users.get_mut(self.uri) else {
+            return;
+        };
+        users.waiting -= 1;
+        if users.waiting == 0
+            && let Ok(_user) = users.lock.try_lock()
+            && let Err(e) = self.overlays.close(self.server, self.uri)
+        {
+            tracing::error!("Failed to close overlay for {}: {:#}", self.uri, e);
+        }
+    }
+}
+
+impl Overlays {
+    async fn user(&self, server: &Server, uri: &Url) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut users = self.users.lock().unwrap();
+            let users = users.entry(uri.clone()).or_default();
+            users.waiting += 1;
+            users.lock.clone()
+        };
+        let _waiting = Waiting {
+            overlays: self,
+            server,
+            uri,
+        };
+        lock.lock_owned().await
+    }
+
+    pub async fn open(
+        self: &Arc<Self>,
+        server: &Server,
+        uri: Url,
+        text: String,
+    ) -> Result<Overlay> {
+        let user = self.user(server, &uri).await;
+        // Closes the document again if notifying rust-analyzer fails
+        let mut overlay = Overlay {
+            overlays: self.clone(),
+            server: server.clone(),
+            uri: uri.clone(),
+            version: 0,
+            user: Some(user),
+        };
+
+        let mut documents = self.documents.lock().unwrap();
+        if let Some(document) = documents.get_mut(&uri) {
+            if let Some(change) = content_change(&document.text, &text) {
+                document.version = self.next_version.fetch_add(1, Ordering::Relaxed);
+                document.text = text;
+                server
+                    .notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+                        text_document: VersionedTextDocumentIdentifier {
+                            uri,
+                            version: document.version,
+                        },
+                        content_changes: vec![change],
+                    })
+                    .context("Sending DidChange notification failed")?;
+            }
+            overlay.version = document.version;
+            return Ok(overlay);
+        }
+
+        let version = self.next_version.fetch_add(1, Ordering::Relaxed);
+        server
+            .notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "rust".into(),
+                    version,
+                    text: text.clone(),
+                },
+            })
+            .context("Sending DidOpen notification failed")?;
+        documents.insert(uri, OverlayDocument { version, text });
+        overlay.version = version;
+        Ok(overlay)
+    }
+
+    /// Waits until no overlay of the document is open and keeps others from opening one until
+    /// the returned lock is dropped.
+    pub async fn lock(&self, server: &Server, uri: &Url) -> Result<DocumentLock> {
+        let user = self.user(server, uri).await;
+        // An overlay that was handed over
+        self.close(server, uri)?;
+        Ok(DocumentLock { _user: user })
+    }
+
+    fn close(&self, server: &Server, uri: &Url) -> Result<()> {
+        let mut documents = self.documents.lock().unwrap();
+        if documents.remove(uri).is_none() {
+            return Ok(());
+        }
+        server
+            .notify::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+            })
+            .context("Sending DidClose notification failed")
+    }
+
+    /// Opens every overlay on a restarted server before it replaces `server`, so that no
+    /// overlay opened in the meantime is sent to the old server only.
+    pub fn reopen(&self, server: &Server, restarted: Server) -> Result<()> {
+        let documents = self.documents.lock().unwrap();
+        for (uri, document) in documents.iter() {
+            restarted
+                .notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                    text_document: TextDocumentItem {
+                        uri: uri.clone(),
+                        language_id: "rust".into(),
+                        version: document.version,
+                        text: document.text.clone(),
+                    },
+                })
+                .context("Sending DidOpen notification failed")?;
+        }
+        server.replace(restarted);
+        Ok(())
+    }
+}
+
+fn content_change(old: &str, new: &str) -> Option<TextDocumentContentChangeEvent> {
+    if old == new {
+        return None;
+    }
+    let prefix = old
+        .chars()
+        .zip(new.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum::<usize>();
+    let suffix = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum::<usize>();
+    Some(TextDocumentContentChangeEvent {
+        range: Some(Range::new(
+            offset_to_position(old, prefix),
+            offset_to_position(old, old.len() - suffix),
+        )),
+        range_length: None,
+        text: new[prefix..new.len() - suffix].to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::TextEdit;
+
+    use super::*;
+    use crate::lsp::apply_text_edits;
+    use crate::lsp::server::tests::recording_server;
+
+    #[test]
+    fn test_content_change() {
+        let old = "fn main() {\n    let ä = 1;\n}\n";
+        let new = "fn main() {\n    let ä = vec![1];\n    ä.len();\n}\n";
+        let change = content_change(old, new).unwrap();
+        let range = change.range.unwrap();
+        assert_eq!(range.start.line, 1);
+        assert_eq!(range.start.character, 12);
+        let edit = TextEdit {
+            range,
+            new_text: change.text,
+        };
+        assert_eq!(apply_text_edits(old, &[edit]).unwrap(), new);
+        assert!(content_change(old, old).is_none());
+    }
+
+    async fn wait_for_waiter(overlays: &Overlays, uri: &Url) {
+        while overlays.users.lock().unwrap()[uri].waiting == 0 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hand_over() {
+        let overlays = Arc::new(Overlays::default());
+        let (server, mut messages) = recording_server();
+        let uri = Url::parse("file:///project/src/lib.rs").unwrap();
+        let first = overlays
+            .open(&server, uri.clone(), "fn main() {}\n".to_string())
+            .await
+            .unwrap();
+        assert_eq!(messages.next().await["method"], "textDocument/didOpen");
+
+        let second = tokio::spawn({
+            let (overlays, server, uri) = (overlays.clone(), server.clone(), uri.clone());
+            async move {
+                overlays
+                    .open(&server, uri, "fn main() { 1; }\n".to_string())
+                    .await
+                    .unwrap()
+            }
+        });
+        wait_for_waiter(&overlays, &uri).await;
+        drop(first);
+        let second = second.await.unwrap();
+        let changed = messages.next().await;
+        assert_eq!(changed["method"], "textDocument/didChange");
+        assert_eq!(
+            changed["params"]["textDocument"]["version"],
+            second.version()
+        );
+        assert_eq!(changed["params"]["contentChanges"][0]["text"], " 1; ");
+
+        // A caller without content waits for the overlay and gets the file on disk
+        let lock = tokio::spawn({
+            let (overlays, server, uri) = (overlays.clone(), server.clone(), uri.clone());
+            async move { overlays.lock(&server, &uri).await.unwrap() }
+        });
+        wait_for_waiter(&overlays, &uri).await;
+        drop(second);
+        let _lock = lock.await.unwrap();
+        assert_eq!(messages.next().await["method"], "textDocument/didClose");
+        assert!(overlays.documents.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reopen() {
+        let overlays = Arc::new(Overlays::default());
+        let (server, mut messages) = recording_server();
+        let uri = Url::parse("file:///project/src/lib.rs").unwrap();
+        let overlay = overlays
+            .open(&server, uri.clone(), "fn main() {}".to_string())
+            .await
+            .unwrap();
+        let opened = messages.next().await;
+        assert_eq!(opened["method"], "textDocument/didOpen");
+
+        let (restarted, mut restarted_messages) = recording_server();
+        overlays.reopen(&server, restarted).unwrap();
+        let reopened = restarted_messages.next().await;
+        assert_eq!(reopened["method"], "textDocument/didOpen");
+        assert_eq!(reopened["params"], opened["params"]);
+        assert_eq!(
+            reopened["params"]["textDocument"]["version"],
+            overlay.version()
+        );
+
+        drop(overlay);
+        let closed = restarted_messages.next().await;
+        assert_eq!(closed["method"], "textDocument/didClose");
+        assert_eq!(closed["params"]["textDocument"]["uri"], uri.as_str());
+    }
+}
@@ -1,135 +1,138 @@
 use std::path::Path;
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
+use async_lsp::ErrorCode;
 use async_lsp::concurrency::ConcurrencyLayer;
 use async_lsp::panic::CatchUnwindLayer;
 use async_lsp::server::LifecycleLayer;
 use async_lsp::tracing::TracingLayer;
-use async_lsp::{LanguageServer, ServerSocket};
-use lsp_types::request::GotoTypeDefinitionParams;
+use futures::AsyncBufReadExt;
+use lsp_types::notification::{DidChangeConfiguration, Exit, Initialized};
+use lsp_types::request::{
+    CallHierarchyIncomingCalls, CallHierarchyOutgoingCalls, CallHierarchyPrepare,
+    CodeActionRequest, CodeActionResolveRequest, Completion, DocumentSymbolRequest, GotoDefinition,
+    GotoImplementation, GotoImplementationParams, GotoImplementationResponse, GotoTypeDefinition,
+    GotoTypeDefinitionParams, HoverRequest, Initialize, InlayHintRequest, PrepareRenameRequest,
+    References, Rename, Request, ResolveCompletionItem, Shutdown, SignatureHelpRequest,
+};
 use lsp_types::{
-    ClientCapabilities, DidOpenTextDocumentParams, DocumentSymbolClientCapabilities,
-    GotoDefinitionResponse, Hover, HoverClientCapabilities, HoverParams, InitializeParams,
-    InitializedParams, Location, MarkupKind, Position, ReferenceContext, ReferenceParams,
-    TextDocumentClientCapabilities, TextDocumentIdentifier, TextDocumentItem,
-    TextDocumentPositionParams, WindowClientCapabilities, WorkDoneProgressParams, WorkspaceFolder,
+    CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams, CallHierarchyItem,
+    CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams,
+    ClientCapabilities, CodeAction, CodeActionCapabilityResolveSupport,
+    CodeActionClientCapabilities, CodeActionContext, CodeActionKind, CodeActionKindLiteralSupport,
+    CodeActionLiteralSupport, CodeActionParams, CodeActionResponse, CodeActionTriggerKind,
+    CompletionClientCapabilities, CompletionContext, CompletionItem, CompletionItemCapability,
+    CompletionItemCapabilityResolveSupport, CompletionParams, CompletionResponse,
+    CompletionTriggerKind, Diagnostic, DidChangeConfigurationParams, DocumentSymbol,
+    DocumentSymbolClientCapabilities, GotoDefinitionParams, GotoDefinitionResponse, Hover,
+    HoverClientCapabilities, HoverParams, InitializeParams, InitializedParams, InlayHint,
+    InlayHintClientCapabilities, InlayHintParams, Location, MarkupKind,
+    ParameterInformationSettings, Position, PrepareRenameResponse, Range, ReferenceContext,
+    ReferenceParams, RenameClientCapabilities, RenameParams, ResourceOperationKind, SignatureHelp,
+    SignatureHelpClientCapabilities, SignatureHelpParams, SignatureInformationSettings,
+    TextDocumentClientCapabilities, TextDocumentIdentifier, TextDocumentPositionParams,
+    WindowClientCapabilities, WorkDoneProgressParams, WorkspaceClientCapabilities, WorkspaceEdit,
+    WorkspaceEditClientCapabilities, WorkspaceFolder, WorkspaceSymbolResponse,
 };
 use serde_json::json;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, watch};
 use tokio::task::JoinHandle;
 use tower::ServiceBuilder;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use super::change_notifier::ChangeNotifier;
 use super::client_state::ClientState;
+use super::config::RustAnalyzerConfig;
+use super::diagnostics::DiagnosticsStore;
+use super::ext::{
+    ExpandMacro, ExpandMacroParams, ExpandedMacro, RecursiveMemoryLayout, RelatedTests, Runnable,
+    Runnables, RunnablesParams, Ssr, SsrParams, SyntaxTree, SyntaxTreeParams, ViewCrateGraph,
+    ViewCrateGraphParams, ViewHir, ViewRecursiveMemoryLayout, ViewSyntaxTree, ViewSyntaxTreeParams,
+    WorkspaceSymbol, WorkspaceSymbolParams, WorkspaceSymbolSearchKind, WorkspaceSymbolSearchScope,
+};
+use super::indexing::IndexingStatus;
+use super::overlays::{DocumentLock, Overlay, Overlays};
+use super::server::Server;
+use super::server_log::ServerLog;
+use super::utils::{format_syntax_tree, symbol_information_to_document_symbol};
 use crate::lsp::LspNotification;
 use crate::project::Project;
 use flume::Sender;
 
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+// A server that stayed up this long is considered healthy again and the backoff starts over
+const RESTART_BACKOFF_RESET: Duration = Duration::from_secs(300);
+const MAX_RESTARTS: u32 = 5;
+
+// rust-analyzer gives up on requests when the workspace changes while it computes them
+const RETRIED_ERRORS: &[ErrorCode] = &[ErrorCode::CONTENT_MODIFIED, ErrorCode::SERVER_CANCELLED];
+const MAX_REQUEST_ATTEMPTS: u32 = 3;
+const REQUEST_RETRY_DELAY: Duration = Duration::from_millis(200);
+
 #[derive(Debug)]
 pub struct RustAnalyzerLsp {
     project: Project,
-    server: Arc<Mutex<ServerSocket>>,
-    #[allow(dead_code)]
-    mainloop_handle: Mutex<Option<JoinHandle<()>>>,
+    server: Server,
+    config: Arc<RwLock<RustAnalyzerConfig>>,
+    supervisor: Mutex<Option<JoinHandle<()>>>,
+    stopping: Arc<AtomicBool>,
     indexed_rx: Mutex<flume::Receiver<()>>,
+    indexing: watch::Receiver<IndexingStatus>,
     #[allow(dead_code)]
     change_notifier: ChangeNotifier,
+    overlays: Arc<Overlays>,
 }
 
 impl RustAnalyzerLsp {
-    pub async fn new(project: &Project, notifier: Sender<LspNotification>) -> Result<Self> {
+    pub async fn new(
+        project: &Project,
+        notifier: Sender<LspNotification>,
+        diagnostics: DiagnosticsStore,
+        log: ServerLog,
+    ) -> Result<Self> {
         let (indexed_tx, indexed_rx) = flume::unbounded();
-        let (mainloop, server) = async_lsp::MainLoop::new_client(|_server| {
-            ServiceBuilder::new()
-                .layer(TracingLayer::default())
-                .layer(LifecycleLayer::default())
-                .layer(CatchUnwindLayer::default())
-                .layer(ConcurrencyLayer::default())
-                .service(ClientState::new_router(
-                    indexed_tx,
-                    notifier,
-                    project.root().to_path_buf(),
-                ))
-        });
-
-        let process = async_process::Command::new("rust-analyzer")
-            .current_dir(project.root())
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .context("Failed run rust-analyzer")?;
-
-        let stdout = process.stdout.context("Failed to get stdout")?;
-        let stdin = process.stdin.context("Failed to get stdin")?;
-
-        let mainloop_handle = tokio::spawn(async move {
-            match mainloop.run_buffered(stdout, stdin).await {
-                Ok(()) => debug!("LSP mainloop finished gracefully."),
-                Err(e) => tracing::error!("LSP mainloop finished with error: {}", e),
-            }
-        });
-
-        let server = Arc::new(Mutex::new(server));
+        let config = Arc::new(RwLock::new(project.rust_analyzer.clone()));
+        let (indexing_tx, indexing) = watch::channel(IndexingStatus::started());
+        let launcher = Launcher {
+            project: project.clone(),
+            config: config.clone(),
+            notifier,
+            diagnostics,
+            indexed_tx,
+            indexing: indexing_tx,
+            log,
+        };
+        let (server, mainloop) = launcher.launch().await?;
+        let change_notifier = ChangeNotifier::new(server.clone(), project, config.clone())?;
 
-        let handle = tokio::runtime::Handle::current();
-        let change_notifier = ChangeNotifier::new(server.clone(), project, handle)?;
+        let overlays = Arc::new(Overlays::default());
+        let stopping = Arc::new(AtomicBool::new(false));
+        let supervisor = Supervisor {
+            launcher,
+            backoff: RESTART_BACKOFF,
+            server: server.clone(),
+            overlays: overlays.clone(),
+            stopping: stopping.clone(),
+        };
+        let supervisor = tokio::spawn(supervisor.run(mainloop));
 
         let client = Self {
             project: project.clone(),
             server,
-            mainloop_handle: Mutex::new(Some(mainloop_handle)),
+            config,
+            supervisor: Mutex::new(Some(supervisor)),
+            stopping,
             indexed_rx: Mutex::new(indexed_rx),
+            indexing,
             change_notifier,
+            overlays,
         };
 
-        let init_ret = client
-            .server
-            .lock()
-            .await
-            .initialize(InitializeParams {
-                workspace_folders: Some(vec![WorkspaceFolder {
-                    uri: project.uri()?,
-                    name: "root".into(),
-                }]),
-                capabilities: ClientCapabilities {
-                    window: Some(WindowClientCapabilities {
-                        work_done_progress: Some(true),
-                        ..WindowClientCapabilities::default()
-                    }),
-                    text_document: Some(TextDocumentClientCapabilities {
-                        document_symbol: Some(DocumentSymbolClientCapabilities {
-                            hierarchical_document_symbol_support: Some(false),
-                            ..DocumentSymbolClientCapabilities::default()
-                        }),
-                        hover: Some(HoverClientCapabilities {
-                            content_format: Some(vec![MarkupKind::Markdown]),
-                            ..HoverClientCapabilities::default()
-                        }),
-                        ..TextDocumentClientCapabilities::default()
-                    }),
-                    experimental: Some(json!({
-                        "hoverActions": true
-                    })),
-                    ..ClientCapabilities::default()
-                },
-                ..InitializeParams::default()
-            })
-            .await
-            .context("LSP initialize failed")?;
-        tracing::trace!("Initialized: {init_ret:?}");
-        info!("LSP Initialized");
-
-        client
-            .server
-            .lock()
-            .await
-            .initialized(InitializedParams {})
-            .context("Sending Initialized notification failed")?;
-
         info!("Waiting for rust-analyzer indexing...");
         let rx = client.indexed_rx.lock().await.clone();
         tokio::spawn(async move {
@@ -142,47 +145,119 @@ impl RustAnalyzerLsp {
     }
 
     pub async fn shutdown(&self) -> Result<()> {
-        self.server
-            .lock()
-            .await
-            .shutdown(())
+        self.stopping.store(true, Ordering::SeqCst);
+        self.request::<Shutdown>(())
             .await
             .context("Sending Shutdown request failed")?;
         self.server
-            .lock()
-            .await
-            .exit(())
+            .notify::<Exit>(())
             .context("Sending Exit notification failed")?;
 
-        if let Err(e) = self.mainloop_handle.lock().await.take().unwrap().await {
-            tracing::error!("Error joining LSP mainloop task: {:?}", e);
+        if let Some(supervisor) = self.supervisor.lock().await.take()
+            && let Err(e) = supervisor.await
+        {
+            tracing::error!("Error joining LSP supervisor task: {:?}", e);
         }
 
         Ok(())
     }
 
-    #[allow(dead_code)]
-    pub async fn open_file(&self, relative_path: impl AsRef<Path>, text: String) -> Result<()> {
-        let uri = self.project.file_uri(relative_path)?;
+    pub fn config(&self) -> RustAnalyzerConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn indexing_status(&self) -> IndexingStatus {
+        self.indexing.borrow().clone()
+    }
+
+    /// Waits at most `timeout` for rust-analyzer to finish indexing and returns how far it got.
+    pub async fn wait_for_indexing(&self, timeout: Duration) -> IndexingStatus {
+        let mut indexing = self.indexing.clone();
+        // Fails once rust-analyzer crashed for good, which leaves the last status
+        let _ =
+            tokio::time::timeout(timeout, indexing.wait_for(|status| !status.is_indexing)).await;
+        self.indexing_status()
+    }
+
+    /// Sends changed settings to rust-analyzer. A changed binary or arguments are only used
+    /// once rust-analyzer restarts.
+    pub async fn update_config(&self, config: RustAnalyzerConfig) -> Result<()> {
+        let settings = config.settings();
+        {
+            let mut current = self.config.write().unwrap();
+            if *current == config {
+                return Ok(());
+            }
+            if current.binary != config.binary || current.args != config.args {
+                tracing::warn!(
+                    "New rust-analyzer binary or arguments for {:?} apply once it restarts",
+                    self.project.root()
+                );
+            }
+            *current = config;
+        }
         self.server
-            .lock()
-            .await
-            .did_open(DidOpenTextDocumentParams {
-                text_document: TextDocumentItem {
-                    uri: uri.clone(),
-                    language_id: "rust".into(),
-                    version: 0,
-                    text,
-                },
+            .notify::<DidChangeConfiguration>(DidChangeConfigurationParams {
+                settings: json!({ "rust-analyzer": settings }),
             })
-            .context("Sending DidOpen notification failed")?;
-        self.indexed_rx
-            .lock()
-            .await
-            .recv_async()
-            .await
-            .context("Failed waiting for index")?;
-        Ok(())
+            .context("Sending DidChangeConfiguration notification failed")
+    }
+
+    /// Sends a request and waits for its response until the configured timeout, retrying
+    /// when rust-analyzer cancelled it because the workspace changed.
+    async fn request<R: Request + 'static>(&self, params: R::Params) -> Result<R::Result>
+    where
+        R::Params: Clone,
+    {
+        let timeout = self.config.read().unwrap().request_timeout(R::METHOD);
+        let deadline = Instant::now() + timeout;
+        let timed_out = || {
+            anyhow!(
+                "{} did not finish within {}s, rust-analyzer might still be indexing. The timeout \
+                 can be raised with `request_timeouts` in the configuration file",
+                R::METHOD,
+                timeout.as_secs()
+            )
+        };
+        let mut attempt = 1;
+        loop {
+            let (id, response) = self.server.send::<R>(params.clone());
+            match tokio::time::timeout_at(deadline.into(), response).await {
+                Ok(Ok(result)) => return Ok(result),
+                Ok(Err(async_lsp::Error::Response(e)))
+                    if RETRIED_ERRORS.contains(&e.code) && attempt < MAX_REQUEST_ATTEMPTS =>
+                {
+                    debug!("{} failed with {}, retrying", R::METHOD, e);
+                    tokio::time::sleep(REQUEST_RETRY_DELAY * attempt).await;
+                    attempt += 1;
+                }
+                Ok(Err(e)) => return Err(e.into()),
+                Err(_) => {
+                    if let Err(e) = self.server.cancel(id) {
+                        tracing::error!("Failed to cancel {}: {}", R::METHOD, e);
+                    }
+                    return Err(timed_out());
+                }
+            }
+        }
+    }
+
+    /// Lets rust-analyzer see `text` instead of the file on disk until the returned overlay is
+    /// dropped.
+    pub async fn open_overlay(
+        &self,
+        relative_path: impl AsRef<Path>,
+        text: String,
+    ) -> Result<Overlay> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.overlays.open(&self.server, uri, text).await
+    }
+
+    /// Waits for other users of an overlay of the file to finish and lets rust-analyzer see the
+    /// file on disk until the returned lock is dropped.
+    pub async fn lock_document(&self, relative_path: impl AsRef<Path>) -> Result<DocumentLock> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.overlays.lock(&self.server, &uri).await
     }
 
     pub async fn hover(
@@ -191,18 +266,33 @@ impl RustAnalyzerLsp {
         position: Position,
     ) -> Result<Option<Hover>> {
         let uri = self.project.file_uri(relative_path)?;
-        self.server
-            .lock()
-            .await
-            .hover(HoverParams {
-                text_document_position_params: TextDocumentPositionParams {
-                    text_document: TextDocumentIdentifier { uri },
-                    position,
-                },
-                work_done_progress_params: WorkDoneProgressParams::default(),
-            })
-            .await
-            .context("Hover request failed")
+        self.request::<HoverRequest>(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        })
+        .await
+        .context("Hover request failed")
+    }
+
+    pub async fn definition(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<GotoDefinition>(GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .context("Definition request failed")
     }
 
     pub async fn type_definition(
@@ -211,19 +301,34 @@ impl RustAnalyzerLsp {
         position: Position,
     ) -> Result<Option<GotoDefinitionResponse>> {
         let uri = self.project.file_uri(relative_path)?;
-        self.server
-            .lock()
-            .await
-            .type_definition(GotoTypeDefinitionParams {
-                text_document_position_params: TextDocumentPositionParams {
-                    text_document: TextDocumentIdentifier { uri },
-                    position,
-                },
-                work_done_progress_params: WorkDoneProgressParams::default(),
-                partial_result_params: Default::default(),
-            })
-            .await
-            .context("Type definition request failed")
+        self.request::<GotoTypeDefinition>(GotoTypeDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .context("Type definition request failed")
+    }
+
+    pub async fn implementations(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Option<GotoImplementationResponse>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<GotoImplementation>(GotoImplementationParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .context("Implementation request failed")
     }
 
     pub async fn find_references(
@@ -232,47 +337,790 @@ impl RustAnalyzerLsp {
         position: Position,
     ) -> Result<Option<Vec<Location>>> {
         let uri = self.project.file_uri(relative_path)?;
-        self.server
-            .lock()
-            .await
-            .references(ReferenceParams {
-                text_document_position: TextDocumentPositionParams {
-                    text_document: TextDocumentIdentifier { uri },
-                    position,
-                },
-                work_done_progress_params: WorkDoneProgressParams::default(),
-                partial_result_params: Default::default(),
-                context: ReferenceContext {
-                    include_declaration: true,
-                },
-            })
-            .await
-            .context("References request failed")
+        self.request::<References>(ReferenceParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+            context: ReferenceContext {
+                include_declaration: true,
+            },
+        })
+        .await
+        .context("References request failed")
+    }
+
+    pub async fn prepare_call_hierarchy(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<CallHierarchyPrepare>(CallHierarchyPrepareParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        })
+        .await
+        .context("Prepare call hierarchy request failed")
+    }
+
+    pub async fn incoming_calls(
+        &self,
+        item: CallHierarchyItem,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        self.request::<CallHierarchyIncomingCalls>(CallHierarchyIncomingCallsParams {
+            item,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .context("Incoming calls request failed")
+    }
+
+    pub async fn outgoing_calls(
+        &self,
+        item: CallHierarchyItem,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        self.request::<CallHierarchyOutgoingCalls>(CallHierarchyOutgoingCallsParams {
+            item,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .context("Outgoing calls request failed")
     }
 
     pub async fn document_symbols(
         &self,
         relative_path: impl AsRef<Path>,
-    ) -> Result<Option<Vec<lsp_types::SymbolInformation>>> {
+    ) -> Result<Option<Vec<DocumentSymbol>>> {
         let uri = self.project.file_uri(relative_path)?;
         let o = self
-            .server
-            .lock()
-            .await
-            .document_symbol(lsp_types::DocumentSymbolParams {
+            .request::<DocumentSymbolRequest>(lsp_types::DocumentSymbolParams {
                 text_document: TextDocumentIdentifier { uri },
                 work_done_progress_params: WorkDoneProgressParams::default(),
                 partial_result_params: Default::default(),
             })
             .await
             .context("Document symbols request failed")?
-            .and_then(|symbols| match symbols {
-                lsp_types::DocumentSymbolResponse::Flat(f) => Some(f),
-                lsp_types::DocumentSymbolResponse::Nested(_) => {
-                    tracing::error!("Only support flat symbols for now");
-                    None
-                }
+            .map(|symbols| match symbols {
+                lsp_types::DocumentSymbolResponse::Flat(f) => f
+                    .into_iter()
+                    .map(symbol_information_to_document_symbol)
+                    .collect(),
+                lsp_types::DocumentSymbolResponse::Nested(n) => n,
             });
         Ok(o)
     }
+
+    pub async fn workspace_symbols(
+        &self,
+        query: &str,
+        include_dependencies: bool,
+    ) -> Result<Option<WorkspaceSymbolResponse>> {
+        let search_scope = if include_dependencies {
+            WorkspaceSymbolSearchScope::WorkspaceAndDependencies
+        } else {
+            WorkspaceSymbolSearchScope::Workspace
+        };
+        self.request::<WorkspaceSymbol>(WorkspaceSymbolParams {
+            partial_result_params: Default::default(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            query: query.to_string(),
+            search_scope: Some(search_scope),
+            search_kind: Some(WorkspaceSymbolSearchKind::AllSymbols),
+        })
+        .await
+        .context("Workspace symbols request failed")
+    }
+
+    pub async fn code_actions(
+        &self,
+        relative_path: impl AsRef<Path>,
+        range: Range,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Result<Option<CodeActionResponse>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier { uri },
+            range,
+            context: CodeActionContext {
+                diagnostics,
+                only: None,
+                trigger_kind: Some(CodeActionTriggerKind::INVOKED),
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .context("Code action request failed")
+    }
+
+    pub async fn resolve_code_action(&self, action: CodeAction) -> Result<CodeAction> {
+        self.request::<CodeActionResolveRequest>(action)
+            .await
+            .context("Code action resolve request failed")
+    }
+
+    pub async fn prepare_rename(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<PrepareRenameRequest>(TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri },
+            position,
+        })
+        .await
+        .context("Prepare rename request failed")
+    }
+
+    pub async fn rename(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+        new_name: &str,
+    ) -> Result<Option<WorkspaceEdit>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<Rename>(RenameParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            new_name: new_name.to_string(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        })
+        .await
+        .context("Rename request failed")
+    }
+
+    pub async fn inlay_hints(
+        &self,
+        relative_path: impl AsRef<Path>,
+        range: Range,
+    ) -> Result<Option<Vec<InlayHint>>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<InlayHintRequest>(InlayHintParams {
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            text_document: TextDocumentIdentifier { uri },
+            range,
+        })
+        .await
+        .context("Inlay hint request failed")
+    }
+
+    pub async fn signature_help(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Option<SignatureHelp>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<SignatureHelpRequest>(SignatureHelpParams {
+            context: None,
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        })
+        .await
+        .context("Signature help request failed")
+    }
+
+    pub async fn completion(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Option<CompletionResponse>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<Completion>(CompletionParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: Default::default(),
+            context: Some(CompletionContext {
+                trigger_kind: CompletionTriggerKind::INVOKED,
+                trigger_character: None,
+            }),
+        })
+        .await
+        .context("Completion request failed")
+    }
+
+    pub async fn resolve_completion_item(&self, item: CompletionItem) -> Result<CompletionItem> {
+        self.request::<ResolveCompletionItem>(item)
+            .await
+            .context("Completion item resolve request failed")
+    }
+
+    pub async fn expand_macro(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Option<ExpandedMacro>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<ExpandMacro>(ExpandMacroParams {
+            text_document: TextDocumentIdentifier { uri },
+            position,
+        })
+        .await
+        .context("Expand macro request failed")
+    }
+
+    pub async fn syntax_tree(
+        &self,
+        relative_path: impl AsRef<Path>,
+        range: Option<Range>,
+    ) -> Result<String> {
+        let uri = self.project.file_uri(relative_path)?;
+        let view = self
+            .request::<ViewSyntaxTree>(ViewSyntaxTreeParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+            })
+            .await;
+        match view {
+            Ok(json) => Ok(format_syntax_tree(&json, range)),
+            // Versions before `viewSyntaxTree` only know `syntaxTree`
+            Err(e) if is_method_not_found(&e) => self
+                .request::<SyntaxTree>(SyntaxTreeParams {
+                    text_document: TextDocumentIdentifier { uri },
+                    range,
+                })
+                .await
+                .context("Syntax tree request failed"),
+            Err(e) => Err(e.context("Syntax tree request failed")),
+        }
+    }
+
+    pub async fn view_hir(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<String> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<ViewHir>(TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri },
+            position,
+        })
+        .await
+        .context("View HIR request failed")
+    }
+
+    pub async fn memory_layout(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Option<RecursiveMemoryLayout>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<ViewRecursiveMemoryLayout>(TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri },
+            position,
+        })
+        .await
+        .context("Memory layout request failed")
+    }
+
+    pub async fn crate_graph(&self, include_dependencies: bool) -> Result<String> {
+        self.request::<ViewCrateGraph>(ViewCrateGraphParams {
+            full: include_dependencies,
+        })
+        .await
+        .context("Crate graph request failed")
+    }
+
+    pub async fn related_tests(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+    ) -> Result<Vec<Runnable>> {
+        let uri = self.project.file_uri(relative_path)?;
+        let tests = self
+            .request::<RelatedTests>(TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            })
+            .await
+            .context("Related tests request failed")?;
+        Ok(tests.into_iter().map(|test| test.runnable).collect())
+    }
+
+    pub async fn runnables(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Option<Position>,
+    ) -> Result<Vec<Runnable>> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<Runnables>(RunnablesParams {
+            text_document: TextDocumentIdentifier { uri },
+            position,
+        })
+        .await
+        .context("Runnables request failed")
+    }
+
+    pub async fn structural_search_replace(
+        &self,
+        relative_path: impl AsRef<Path>,
+        position: Position,
+        rule: &str,
+        selections: Vec<Range>,
+    ) -> Result<WorkspaceEdit> {
+        let uri = self.project.file_uri(relative_path)?;
+        self.request::<Ssr>(SsrParams {
+            query: rule.to_string(),
+            parse_only: false,
+            position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            selections,
+        })
+        .await
+        .context("Structural search replace request failed")
+    }
+}
+
+impl Drop for RustAnalyzerLsp {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::SeqCst);
+    }
+}
+
+struct Launcher {
+    project: Project,
+    config: Arc<RwLock<RustAnalyzerConfig>>,
+    notifier: Sender<LspNotification>,
+    diagnostics: DiagnosticsStore,
+    indexed_tx: Sender<()>,
+    indexing: watch::Sender<IndexingStatus>,
+    log: ServerLog,
+}
+
+impl Launcher {
+    /// Starts and initializes rust-analyzer. The returned task finishes with the reason the
+    /// server stopped.
+    async fn launch(&self) -> Result<(Server, JoinHandle<String>)> {
+        let config = self.config.read().unwrap().clone();
+        self.indexing.send_replace(IndexingStatus::started());
+        let router = ClientState::new_router(
+            self.indexed_tx.clone(),
+            self.notifier.clone(),
+            self.project.root().to_path_buf(),
+            self.diagnostics.clone(),
+            self.config.clone(),
+            self.indexing.clone(),
+            self.log.clone(),
+        );
+        let (mainloop, server) = async_lsp::MainLoop::new_client(|_server| {
+            ServiceBuilder::new()
+                .layer(TracingLayer::default())
+                .layer(LifecycleLayer::default())
+                .layer(CatchUnwindLayer::default())
+                .layer(ConcurrencyLayer::default())
+                .service(router)
+        });
+
+        let mut process = async_process::Command::new(config.binary())
+            .args(&config.args)
+            .current_dir(self.project.root())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to run {:?}", config.binary()))?;
+
+        let stdout = process.stdout.take().context("Failed to get stdout")?;
+        let stdin = process.stdin.take().context("Failed to get stdin")?;
+        let stderr = process.stderr.take().context("Failed to get stderr")?;
+
+        let log = self.log.clone();
+        tokio::spawn(async move {
+            let mut stderr = futures::io::BufReader::new(stderr);
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                // Build scripts and proc macros can print anything, not only UTF-8
+                match stderr.read_until(b'\n', &mut line).await {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        let line = String::from_utf8_lossy(&line);
+                        info!("rust-analyzer: {}", line.trim_end());
+                        log.push("stderr", &line);
+                    }
+                    Err(e) => {
+                        warn!("Failed to read the stderr of rust-analyzer: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mainloop = tokio::spawn(async move {
+            let result = mainloop.run_buffered(stdout, stdin).await;
+            // The mainloop also stops on protocol errors, with rust-analyzer still running
+            let _ = process.kill();
+            let status = match process.status().await {
+                Ok(status) => status.to_string(),
+                Err(e) => e.to_string(),
+            };
+            match result {
+                Ok(()) => {
+                    debug!("LSP mainloop finished gracefully.");
+                    format!("rust-analyzer stopped ({status})")
+                }
+                Err(e) => {
+                    tracing::error!("LSP mainloop finished with error: {}", e);
+                    format!("rust-analyzer stopped ({status}): {e}")
+                }
+            }
+        });
+
+        let server = Server::new(server);
+        if let Err(e) = initialize(&server, &self.project, config.settings()).await {
+            mainloop.abort();
+            return Err(e);
+        }
+        Ok((server, mainloop))
+    }
+}
+
+struct Supervisor {
+    launcher: Launcher,
+    // The delay before the first restart, doubled for every further one
+    backoff: Duration,
+    server: Server,
+    overlays: Arc<Overlays>,
+    stopping: Arc<AtomicBool>,
+}
+
+impl Supervisor {
+    /// Restarts rust-analyzer with an exponential backoff whenever it stops without having
+    /// been shut down.
+    async fn run(self, mut mainloop: JoinHandle<String>) {
+        let project = self.launcher.project.root().to_path_buf();
+        let mut started = Instant::now();
+        let mut restarts = 0;
+        loop {
+            let mut reason = match mainloop.await {
+                Ok(reason) => reason,
+                Err(e) => format!("LSP mainloop task failed: {e}"),
+            };
+            if self.stopping.load(Ordering::SeqCst) {
+                return;
+            }
+            self.launcher.diagnostics.clear();
+            if started.elapsed() >= RESTART_BACKOFF_RESET {
+                restarts = 0;
+            }
+            mainloop = loop {
+                if restarts >= MAX_RESTARTS {
+                    tracing::error!(
+                        "Giving up on rust-analyzer for {:?} after {} restarts: {}",
+                        project,
+                        restarts,
+                        reason
+                    );
+                    self.notify(LspNotification::Crashed {
+                        project: project.clone(),
+                        reason,
+                        restart_in: None,
+                    });
+                    return;
+                }
+                let backoff = (self.backoff * 2u32.pow(restarts)).min(MAX_RESTART_BACKOFF);
+                tracing::warn!(
+                    "rust-analyzer for {:?} crashed, restarting in {:?}: {}",
+                    project,
+                    backoff,
+                    reason
+                );
+                self.notify(LspNotification::Crashed {
+                    project: project.clone(),
+                    reason: reason.clone(),
+                    restart_in: Some(backoff),
+                });
+                tokio::time::sleep(backoff).await;
+                if self.stopping.load(Ordering::SeqCst) {
+                    return;
+                }
+                restarts += 1;
+                match self.restart().await {
+                    Ok(mainloop) => break mainloop,
+                    Err(e) => reason = format!("{e:#}"),
+                }
+            };
+            started = Instant::now();
+            info!("Restarted rust-analyzer for {:?}", project);
+            self.notify(LspNotification::Restarted {
+                project: project.clone(),
+                restarts,
+            });
+        }
+    }
+
+    async fn restart(&self) -> Result<JoinHandle<String>> {
+        let (server, mainloop) = self.launcher.launch().await?;
+        if let Err(e) = self.overlays.reopen(&self.server, server) {
+            mainloop.abort();
+            return Err(e);
+        }
+        Ok(mainloop)
+    }
+
+    fn notify(&self, notification: LspNotification) {
+        if let Err(e) = self.launcher.notifier.send(notification) {
+            tracing::error!("Failed to send LSP notification: {}", e);
+        }
+    }
+}
+
+async fn initialize(
+    server: &Server,
+    project: &Project,
+    initialization_options: serde_json::Value,
+) -> Result<()> {
+    let (_, response) = server.send::<Initialize>(InitializeParams {
+        workspace_folders: Some(vec![WorkspaceFolder {
+            uri: project.uri()?,
+            name: "root".into(),
+        }]),
+        capabilities: ClientCapabilities {
+            window: Some(WindowClientCapabilities {
+                work_done_progress: Some(true),
+                ..WindowClientCapabilities::default()
+            }),
+            workspace: Some(WorkspaceClientCapabilities {
+                configuration: Some(true),
+                workspace_edit: Some(WorkspaceEditClientCapabilities {
+                    document_changes: Some(true),
+                    resource_operations: Some(vec![
+                        ResourceOperationKind::Create,
+                        ResourceOperationKind::Rename,
+                        ResourceOperationKind::Delete,
+                    ]),
+                    ..WorkspaceEditClientCapabilities::default()
+                }),
+                ..WorkspaceClientCapabilities::default()
+            }),
+            text_document: Some(TextDocumentClientCapabilities {
+                code_action: Some(CodeActionClientCapabilities {
+                    code_action_literal_support: Some(CodeActionLiteralSupport {
+                        code_action_kind: CodeActionKindLiteralSupport {
+                            value_set: [
+                                CodeActionKind::EMPTY,
+                                CodeActionKind::QUICKFIX,
+                                CodeActionKind::REFACTOR,
+                                CodeActionKind::REFACTOR_EXTRACT,
+                                CodeActionKind::REFACTOR_INLINE,
+                                CodeActionKind::REFACTOR_REWRITE,
+                                CodeActionKind::SOURCE,
+                                CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                            ]
+                            .iter()
+                            .map(|kind| kind.as_str().to_string())
+                            .collect(),
+                        },
+                    }),
+                    data_support: Some(true),
+                    resolve_support: Some(CodeActionCapabilityResolveSupport {
+                        properties: vec!["edit".to_string()],
+                    }),
+                    ..CodeActionClientCapabilities::default()
+                }),
+                inlay_hint: Some(InlayHintClientCapabilities::default()),
+                signature_help: Some(SignatureHelpClientCapabilities {
+                    signature_information: Some(SignatureInformationSettings {
+                        documentation_format: Some(vec![MarkupKind::Markdown]),
+                        parameter_information: Some(ParameterInformationSettings {
+                            label_offset_support: Some(true),
+                        }),
+                        active_parameter_support: Some(true),
+                    }),
+                    ..SignatureHelpClientCapabilities::default()
+                }),
+                rename: Some(RenameClientCapabilities {
+                    prepare_support: Some(true),
+                    ..RenameClientCapabilities::default()
+                }),
+                completion: Some(CompletionClientCapabilities {
+                    completion_item: Some(CompletionItemCapability {
+                        snippet_support: Some(false),
+                        documentation_format: Some(vec![MarkupKind::Markdown]),
+                        label_details_support: Some(true),
+                        // rust-analyzer only completes items that need an import
+                        // if it can defer computing the import to a resolve
+                        resolve_support: Some(CompletionItemCapabilityResolveSupport {
+                            properties: vec!["additionalTextEdits".to_string()],
+                        }),
+                        ..CompletionItemCapability::default()
+                    }),
+                    ..CompletionClientCapabilities::default()
+                }),
+                document_symbol: Some(DocumentSymbolClientCapabilities {
+                    hierarchical_document_symbol_support: Some(true),
+                    ..DocumentSymbolClientCapabilities::default()
+                }),
+                hover: Some(HoverClientCapabilities {
+                    content_format: Some(vec![MarkupKind::Markdown]),
+                    ..HoverClientCapabilities::default()
+                }),
+                ..TextDocumentClientCapabilities::default()
+            }),
+            experimental: Some(json!({
+                "hoverActions": true,
+                "serverStatusNotification": true,
+                "workspaceSymbolScopeKindFiltering": true
+            })),
+            ..ClientCapabilities::default()
+        },
+        initialization_options: Some(initialization_options),
+        ..InitializeParams::default()
+    });
+    let init_ret = response.await.context("LSP initialize failed")?;
+    tracing::trace!("Initialized: {init_ret:?}");
+    info!("LSP Initialized");
+
+    server
+        .notify::<Initialized>(InitializedParams {})
+        .context("Sending Initialized notification failed")
+}
+
+fn is_method_not_found(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<async_lsp::Error>(),
+        Some(async_lsp::Error::Response(e)) if e.code == ErrorCode::METHOD_NOT_FOUND
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::lsp::server::tests::recording_server;
+
+    const HOVERS: u32 = 64;
+
+    #[tokio::test]
+    async fn test_supervisor_gives_up() {
+        let (notifier, notifications) = flume::unbounded();
+        let (indexed_tx, _indexed_rx) = flume::unbounded();
+        let diagnostics = DiagnosticsStore::default();
+        let file = PathBuf::from("/project/src/lib.rs");
+        diagnostics.update(file.clone(), Some(1), vec![Diagnostic::default()]);
+        let launcher = Launcher {
+            project: Project {
+                root: PathBuf::from("/project"),
+                ignore_crates: vec![],
+                rust_analyzer: RustAnalyzerConfig::default(),
+            },
+            config: Arc::new(RwLock::new(RustAnalyzerConfig {
+                binary: Some(PathBuf::from("/nonexistent/rust-analyzer")),
+                ..RustAnalyzerConfig::default()
+            })),
+            notifier,
+            diagnostics: diagnostics.clone(),
+            indexed_tx,
+            indexing: watch::channel(IndexingStatus::started()).0,
+            log: ServerLog::default(),
+        };
+        let backoff = Duration::from_millis(1);
+        let supervisor = Supervisor {
+            launcher,
+            backoff,
+            server: recording_server().0,
+            overlays: Arc::new(Overlays::default()),
+            stopping: Arc::new(AtomicBool::new(false)),
+        };
+        let crashed = tokio::spawn(async { "rust-analyzer stopped".to_string() });
+        supervisor.run(crashed).await;
+
+        assert!(diagnostics.file(&file).is_empty());
+        let restarts_in = notifications
+            .drain()
+            .map(|notification| match notification {
+                LspNotification::Crashed { restart_in, .. } => restart_in,
+                notification => panic!("Unexpected {notification:?}"),
+            })
+            .collect::<Vec<_>>();
+        let mut expected = (0..MAX_RESTARTS)
+            .map(|restart| Some(backoff * 2u32.pow(restart)))
+            .collect::<Vec<_>>();
+        expected.push(None);
+        assert_eq!(restarts_in, expected);
+    }
+
+    // Has 64 documented functions of four lines each
+    fn fixture_project() -> Project {
+        Project::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hover")).unwrap()
+    }
+
+    fn function_position(i: u32) -> Position {
+        Position::new(i * 4 + 1, 7)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "needs rust-analyzer"]
+    async fn test_parallel_hovers_are_faster_than_serialized() {
+        let project = fixture_project();
+        let (notifier, _notifications) = flume::unbounded();
+        let lsp = RustAnalyzerLsp::new(
+            &project,
+            notifier,
+            DiagnosticsStore::default(),
+            ServerLog::default(),
+        )
+        .await
+        .unwrap();
+
+        let warm_up = async {
+            while lsp
+                .hover("src/lib.rs", function_position(0))
+                .await
+                .ok()
+                .flatten()
+                .is_none()
+            {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(120), warm_up)
+            .await
+            .expect("rust-analyzer did not finish indexing the fixture");
+
+        // Requests used to be serialized by a lock around the connection
+        let lock = &Mutex::new(());
+        let lsp = &lsp;
+        let start = Instant::now();
+        let hovers = futures::future::join_all((0..HOVERS).map(|i| async move {
+            let _lock = lock.lock().await;
+            lsp.hover("src/lib.rs", function_position(i)).await
+        }))
+        .await;
+        let serialized = start.elapsed();
+        assert!(hovers.into_iter().all(|hover| hover.unwrap().is_some()));
+
+        let start = Instant::now();
+        let hovers = futures::future::join_all(
+            (0..HOVERS).map(|i| lsp.hover("src/lib.rs", function_position(i))),
+        )
+        .await;
+        let parallel = start.elapsed();
+        assert!(hovers.into_iter().all(|hover| hover.unwrap().is_some()));
+
+        assert!(
+            parallel * 4 < serialized * 3,
+            "{HOVERS} parallel hovers took {parallel:?}, serialized {serialized:?}"
+        );
+        lsp.shutdown().await.unwrap();
+    }
 }
@@ -0,0 +1,225 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_lsp::ServerSocket;
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use lsp_types::notification::{Cancel, Notification};
+use lsp_types::request::Request;
+use lsp_types::{CancelParams, NumberOrString};
+
+/// A handle to rust-analyzer that keeps track of the ids of the requests sent through it, so
+/// that they can be cancelled. Clones share the socket, which is only locked while a message is
+/// queued: requests run concurrently and notifications arrive in the order they were sent.
+#[derive(Debug, Clone)]
+pub struct Server {
+    state: Arc<Mutex<ServerState>>,
+}
+
+#[derive(Debug)]
+struct ServerState {
+    socket: ServerSocket,
+    // async-lsp does not expose the ids of outgoing requests. Its main loop numbers them from 0
+    // in the order they were queued, so this only holds as long as every request of the socket
+    // is sent through `Server::send`, and async-lsp is pinned in `Cargo.toml` for it.
+    next_request_id: i32,
+    // Counts the restarts, ids of an earlier server must not cancel requests of a later one
+    generation: u32,
+}
+
+/// The id of a request sent through a [`Server`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestId {
+    generation: u32,
+    id: i32,
+}
+
+impl Server {
+    pub fn new(socket: ServerSocket) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ServerState {
+                socket,
+                next_request_id: 0,
+                generation: 0,
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, ServerState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Queues a request and returns its id together with the response.
+    pub fn send<R: Request + 'static>(
+        &self,
+        params: R::Params,
+    ) -> (RequestId, BoxFuture<'static, async_lsp::Result<R::Result>>) {
+        let mut state = self.state();
+        let id = RequestId {
+            generation: state.generation,
+            id: state.next_request_id,
+        };
+        state.next_request_id += 1;
+        let socket = state.socket.clone();
+        let mut response: BoxFuture<'static, _> =
+            Box::pin(async move { socket.request::<R>(params).await });
+        // The request is only queued once the response is polled, which has to happen while
+        // the state is locked to keep the ids in order
+        if let Some(result) = response.as_mut().now_or_never() {
+            return (id, Box::pin(std::future::ready(result)));
+        }
+        (id, response)
+    }
+
+    pub fn notify<N: Notification>(&self, params: N::Params) -> async_lsp::Result<()> {
+        self.state().socket.notify::<N>(params)
+    }
+
+    /// Cancels a request, unless it was sent to a server that was replaced since.
+    pub fn cancel(&self, id: RequestId) -> async_lsp::Result<()> {
+        let state = self.state();
+        if state.generation != id.generation {
+            return Ok(());
+        }
+        state.socket.notify::<Cancel>(CancelParams {
+            id: NumberOrString::Number(id.id),
+        })
+    }
+
+    /// Makes this server and all of its clones talk to `restarted` from now on.
+    pub fn replace(&self, restarted: Server) {
+        let mut restarted = restarted.state();
+        let mut state = self.state();
+        restarted.generation = state.generation + 1;
+        std::mem::swap(&mut *state, &mut *restarted);
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use async_lsp::MainLoop;
+    use async_lsp::router::Router;
+    use futures::{AsyncWrite, TryStreamExt};
+    use lsp_types::notification::Exit;
+    use lsp_types::request::HoverRequest;
+    use lsp_types::{
+        HoverParams, Position, TextDocumentIdentifier, TextDocumentPositionParams, Url,
+        WorkDoneProgressParams,
+    };
+    use serde_json::Value;
+
+    use super::*;
+
+    struct Wire(flume::Sender<Vec<u8>>);
+
+    impl AsyncWrite for Wire {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let _ = self.0.send(buf.to_vec());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// The messages a [`Server`] sent, as they were written to the wire.
+    pub struct Messages {
+        wire: flume::Receiver<Vec<u8>>,
+        buffer: Vec<u8>,
+    }
+
+    impl Messages {
+        pub async fn next(&mut self) -> Value {
+            loop {
+                if let Some(header_end) = self.buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                    let header = std::str::from_utf8(&self.buffer[..header_end]).unwrap();
+                    let length: usize = header
+                        .strip_prefix("Content-Length: ")
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+                    let body_start = header_end + 4;
+                    if self.buffer.len() >= body_start + length {
+                        let body: Vec<u8> = self
+                            .buffer
+                            .drain(..body_start + length)
+                            .skip(body_start)
+                            .collect();
+                        return serde_json::from_slice(&body).unwrap();
+                    }
+                }
+                let bytes = self.wire.recv_async().await.unwrap();
+                self.buffer.extend(bytes);
+            }
+        }
+    }
+
+    /// A server that never answers and records the messages sent to it.
+    pub fn recording_server() -> (Server, Messages) {
+        let (mainloop, socket) = MainLoop::new_client(|_server| Router::new(()));
+        let (tx, rx) = flume::unbounded();
+        let input = futures::stream::pending::<io::Result<Vec<u8>>>().into_async_read();
+        // The main loop panics once every socket is gone, which replacing the server does
+        let kept = socket.clone();
+        tokio::spawn(async move {
+            let _socket = kept;
+            mainloop.run(input, Wire(tx)).await
+        });
+        let messages = Messages {
+            wire: rx,
+            buffer: Vec::new(),
+        };
+        (Server::new(socket), messages)
+    }
+
+    fn hover_params() -> HoverParams {
+        HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::parse("file:///project/src/lib.rs").unwrap(),
+                },
+                position: Position::new(0, 0),
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_sends_request_id() {
+        let (server, mut messages) = recording_server();
+        let (first, _first_response) = server.send::<HoverRequest>(hover_params());
+        let (second, _second_response) = server.send::<HoverRequest>(hover_params());
+        server.cancel(second).unwrap();
+        server.cancel(first).unwrap();
+
+        let first_id = messages.next().await["id"].clone();
+        let second_id = messages.next().await["id"].clone();
+        assert_ne!(first_id, second_id);
+        assert_eq!(messages.next().await["params"]["id"], second_id);
+        assert_eq!(messages.next().await["params"]["id"], first_id);
+
+        // A request of the replaced server must not cancel one of the restarted server
+        let (restarted, mut restarted_messages) = recording_server();
+        server.replace(restarted);
+        let (current, _current_response) = server.send::<HoverRequest>(hover_params());
+        server.cancel(first).unwrap();
+        server.notify::<Exit>(()).unwrap();
+        let current_id = restarted_messages.next().await["id"].clone();
+        assert_eq!(current_id, first_id);
+        assert_eq!(restarted_messages.next().await["method"], "exit");
+        server.cancel(current).unwrap();
+        assert_eq!(restarted_messages.next().await["params"]["id"], current_id);
+    }
+}
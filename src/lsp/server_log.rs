@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use lsp_types::MessageType;
+
+const MAX_LINES: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub timestamp: DateTime<Utc>,
+    /// `stderr`, or the type of a message rust-analyzer sent, e.g. `error`
+    pub source: &'static str,
+    pub text: String,
+}
+
+impl fmt::Display for LogLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] {}",
+            self.timestamp.format("%H:%M:%S"),
+            self.source,
+            self.text
+        )
+    }
+}
+
+/// The latest output of a project's rust-analyzer: its stderr and the messages it sent.
+#[derive(Debug, Clone, Default)]
+pub struct ServerLog {
+    lines: Arc<Mutex<VecDeque<LogLine>>>,
+}
+
+impl ServerLog {
+    pub fn push(&self, source: &'static str, text: &str) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == MAX_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(LogLine {
+            timestamp: Utc::now(),
+            source,
+            text: text.trim_end().to_string(),
+        });
+    }
+
+    pub fn push_message(&self, typ: MessageType, text: &str) {
+        let source = match typ {
+            MessageType::ERROR => "error",
+            MessageType::WARNING => "warning",
+            MessageType::INFO => "info",
+            _ => "log",
+        };
+        self.push(source, text);
+    }
+
+    /// The last `count` lines, oldest first.
+    pub fn tail(&self, count: usize) -> Vec<LogLine> {
+        let lines = self.lines.lock().unwrap();
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+
+    /// Calls `f` with all lines, oldest first, without copying them.
+    pub fn with_lines<R>(&self, f: impl FnOnce(&VecDeque<LogLine>) -> R) -> R {
+        f(&self.lines.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail() {
+        let log = ServerLog::default();
+        for i in 0..MAX_LINES + 10 {
+            log.push("stderr", &format!("line {i}\n"));
+        }
+        log.push_message(MessageType::ERROR, "failed to load workspace");
+        let tail = log.tail(2);
+        assert_eq!(tail[0].text, format!("line {}", MAX_LINES + 9));
+        assert_eq!(tail[1].source, "error");
+        assert_eq!(log.tail(usize::MAX).len(), MAX_LINES);
+    }
+}
@@ -1,5 +1,10 @@
-use lsp_types::{GotoDefinitionResponse, MarkedString};
+use lsp_types::{
+    DocumentSymbol, GotoDefinitionResponse, Location, MarkedString, Position, Range,
+    SymbolInformation, SymbolKind,
+};
+use serde_json::Value;
 use std::collections::HashSet;
+use std::fmt::Write;
 use std::fs;
 use std::path::PathBuf;
 use url::Url;
@@ -35,6 +40,82 @@ pub fn get_location_contents(
     Ok(contents)
 }
 
+pub fn get_locations(response: GotoDefinitionResponse) -> Vec<Location> {
+    match response {
+        GotoDefinitionResponse::Scalar(location) => vec![location],
+        GotoDefinitionResponse::Array(locations) => locations,
+        GotoDefinitionResponse::Link(links) => links
+            .into_iter()
+            .map(|link| Location {
+                uri: link.target_uri,
+                range: link.target_selection_range,
+            })
+            .collect(),
+    }
+}
+
+pub fn range_contains(range: &Range, position: Position) -> bool {
+    range.start <= position && position <= range.end
+}
+
+/// Extracts the self type from a rust-analyzer impl symbol name such as
+/// `impl Foo<T>` or `impl fmt::Display for Foo`.
+pub fn impl_self_type(name: &str) -> Option<&str> {
+    let rest = name.strip_prefix("impl ")?;
+    let self_type = rest.rsplit(" for ").next().unwrap_or(rest);
+    let self_type = self_type.split('<').next().unwrap_or(self_type);
+    self_type.rsplit("::").next().map(str::trim)
+}
+
+#[allow(deprecated)]
+pub fn symbol_information_to_document_symbol(symbol: SymbolInformation) -> DocumentSymbol {
+    DocumentSymbol {
+        name: symbol.name,
+        detail: None,
+        kind: symbol.kind,
+        tags: symbol.tags,
+        deprecated: symbol.deprecated,
+        range: symbol.location.range,
+        selection_range: symbol.location.range,
+        children: None,
+    }
+}
+
+/// Flattens nested document symbols into `(qualified name, symbol)` pairs. Items inside
+/// of an impl block are qualified with the self type, e.g. `Docs::update_index`.
+pub fn flatten_document_symbols(symbols: &[DocumentSymbol]) -> Vec<(String, &DocumentSymbol)> {
+    fn walk<'a>(
+        symbols: &'a [DocumentSymbol],
+        prefix: Option<&str>,
+        flattened: &mut Vec<(String, &'a DocumentSymbol)>,
+    ) {
+        for symbol in symbols {
+            let self_type = if symbol.kind == SymbolKind::OBJECT {
+                impl_self_type(&symbol.name)
+            } else {
+                None
+            };
+            let name = self_type.unwrap_or(&symbol.name);
+            let qualified = match prefix {
+                Some(prefix) => format!("{prefix}::{name}"),
+                None => name.to_string(),
+            };
+            let own_name = match self_type {
+                Some(_) => symbol.name.clone(),
+                None => qualified.clone(),
+            };
+            flattened.push((own_name, symbol));
+            if let Some(children) = &symbol.children {
+                walk(children, Some(&qualified), flattened);
+            }
+        }
+    }
+
+    let mut flattened = Vec::new();
+    walk(symbols, None, &mut flattened);
+    flattened
+}
+
 pub fn format_marked_string(marked_string: &MarkedString) -> String {
     match marked_string {
         MarkedString::String(s) => s.clone(),
@@ -45,6 +126,58 @@ pub fn format_marked_string(marked_string: &MarkedString) -> String {
     }
 }
 
+/// Formats the JSON tree of rust-analyzer's `viewSyntaxTree` as one `KIND@start..end` line per
+/// node and token, indented by depth, with 1 based lines and columns. Only nodes and tokens on
+/// the lines of `range` are kept. Falls back to the JSON if it can't be read as a tree.
+pub fn format_syntax_tree(json: &str, range: Option<Range>) -> String {
+    let Ok(root) = serde_json::from_str::<Value>(json) else {
+        return json.to_string();
+    };
+    let mut tree = String::new();
+    write_syntax_element(&mut tree, &root, range, 0);
+    if tree.is_empty() {
+        return json.to_string();
+    }
+    tree
+}
+
+fn write_syntax_element(tree: &mut String, element: &Value, range: Option<Range>, depth: usize) {
+    let line_col = |key: &str| {
+        let line_col = element.get(key)?.as_array()?;
+        Some((line_col.first()?.as_u64()?, line_col.get(1)?.as_u64()?))
+    };
+    let (Some(kind), Some(start), Some(end)) = (
+        element.get("kind").and_then(Value::as_str),
+        line_col("start"),
+        line_col("end"),
+    ) else {
+        return;
+    };
+    if let Some(range) = range
+        && (end.0 < range.start.line as u64 || start.0 > range.end.line as u64)
+    {
+        return;
+    }
+    let _ = writeln!(
+        tree,
+        "{:indent$}{kind}@{}:{}..{}:{}",
+        "",
+        start.0 + 1,
+        start.1 + 1,
+        end.0 + 1,
+        end.1 + 1,
+        indent = depth * 2
+    );
+    for child in element
+        .get("children")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        write_syntax_element(tree, child, range, depth + 1);
+    }
+}
+
 fn url_to_file_path(url: &Url) -> Result<PathBuf, std::io::Error> {
     url.to_file_path().map_err(|_| {
         std::io::Error::new(
@@ -53,3 +186,88 @@ fn url_to_file_path(url: &Url) -> Result<PathBuf, std::io::Error> {
         )
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(deprecated)]
+    fn symbol(name: &str, kind: SymbolKind, children: Vec<DocumentSymbol>) -> DocumentSymbol {
+        DocumentSymbol {
+            name: name.to_string(),
+            detail: None,
+            kind,
+            tags: None,
+            deprecated: None,
+            range: Range::default(),
+            selection_range: Range::default(),
+            children: Some(children),
+        }
+    }
+
+    #[test]
+    fn test_impl_self_type() {
+        assert_eq!(impl_self_type("impl Docs"), Some("Docs"));
+        assert_eq!(impl_self_type("impl Foo<T>"), Some("Foo"));
+        assert_eq!(impl_self_type("impl fmt::Display for Docs"), Some("Docs"));
+        assert_eq!(
+            impl_self_type("impl From<u8> for crate::Foo<u8>"),
+            Some("Foo")
+        );
+        assert_eq!(impl_self_type("Docs"), None);
+    }
+
+    #[test]
+    fn test_flatten_document_symbols() {
+        let symbols = vec![
+            symbol("Docs", SymbolKind::STRUCT, vec![]),
+            symbol(
+                "impl Docs",
+                SymbolKind::OBJECT,
+                vec![symbol("update_index", SymbolKind::METHOD, vec![])],
+            ),
+            symbol(
+                "tests",
+                SymbolKind::MODULE,
+                vec![symbol("test_index", SymbolKind::FUNCTION, vec![])],
+            ),
+        ];
+        let names = flatten_document_symbols(&symbols)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "Docs",
+                "impl Docs",
+                "Docs::update_index",
+                "tests",
+                "tests::test_index"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_syntax_tree() {
+        let json = r#"{"type":"Node","kind":"SOURCE_FILE","start":[0,0],"end":[2,0],"children":[
+            {"type":"Node","kind":"FN","start":[0,0],"end":[0,12],"children":[
+                {"type":"Token","kind":"FN_KW","start":[0,0],"end":[0,2]}]},
+            {"type":"Token","kind":"WHITESPACE","start":[0,12],"end":[1,0]},
+            {"type":"Node","kind":"STRUCT","start":[1,0],"end":[1,9],"children":[]}]}"#;
+        assert_eq!(
+            format_syntax_tree(json, None),
+            "SOURCE_FILE@1:1..3:1\n  FN@1:1..1:13\n    FN_KW@1:1..1:3\n  WHITESPACE@1:13..2:1\n  \
+             STRUCT@2:1..2:10\n"
+        );
+        let second_line = Range::new(Position::new(1, 0), Position::new(1, 9));
+        assert_eq!(
+            format_syntax_tree(json, Some(second_line)),
+            "SOURCE_FILE@1:1..3:1\n  WHITESPACE@1:13..2:1\n  STRUCT@2:1..2:10\n"
+        );
+        assert_eq!(
+            format_syntax_tree("SOURCE_FILE@0..0", None),
+            "SOURCE_FILE@0..0"
+        );
+    }
+}
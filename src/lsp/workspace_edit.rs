@@ -0,0 +1,457 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use lsp_types::{
+    AnnotatedTextEdit, DocumentChangeOperation, DocumentChanges, OneOf, Position, ResourceOp,
+    TextEdit, Url, WorkspaceEdit,
+};
+use similar::{Algorithm, DiffTag};
+
+const DIFF_CONTEXT: usize = 3;
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub original: Option<String>,
+    pub new: Option<String>,
+}
+
+pub fn resolve_workspace_edit(edit: &WorkspaceEdit) -> Result<Vec<FileChange>> {
+    let mut changes = Vec::new();
+
+    match &edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => {
+            for edit in edits {
+                let edits = edit.edits.iter().map(text_edit).collect::<Vec<_>>();
+                apply_to_file(&mut changes, &edit.text_document.uri, &edits)?;
+            }
+        }
+        Some(DocumentChanges::Operations(operations)) => {
+            for operation in operations {
+                match operation {
+                    DocumentChangeOperation::Edit(edit) => {
+                        let edits = edit.edits.iter().map(text_edit).collect::<Vec<_>>();
+                        apply_to_file(&mut changes, &edit.text_document.uri, &edits)?;
+                    }
+                    DocumentChangeOperation::Op(ResourceOp::Create(create)) => {
+                        let change = file_change(&mut changes, &create.uri)?;
+                        let overwrite = create
+                            .options
+                            .as_ref()
+                            .and_then(|options| options.overwrite)
+                            .unwrap_or(false);
+                        if change.new.is_none() || overwrite {
+                            change.new = Some(String::new());
+                        }
+                    }
+                    DocumentChangeOperation::Op(ResourceOp::Rename(rename)) => {
+                        let content = file_change(&mut changes, &rename.old_uri)?
+                            .new
+                            .take()
+                            .with_context(|| {
+                                format!("Cannot rename missing file {}", rename.old_uri)
+                            })?;
+                        file_change(&mut changes, &rename.new_uri)?.new = Some(content);
+                    }
+                    DocumentChangeOperation::Op(ResourceOp::Delete(delete)) => {
+                        file_change(&mut changes, &delete.uri)?.new = None;
+                    }
+                }
+            }
+        }
+        None => {
+            for (uri, edits) in edit.changes.iter().flatten() {
+                apply_to_file(&mut changes, uri, edits)?;
+            }
+        }
+    }
+
+    changes.retain(|change| change.original != change.new);
+    Ok(changes)
+}
+
+/// Writes the new contents to disk. They are written next to their files first and only then
+/// moved into place, so that a failure to write one leaves all files untouched.
+pub fn write_changes(changes: &[FileChange]) -> Result<()> {
+    let mut staged = Vec::new();
+    for change in changes {
+        let temp = match &change.new {
+            Some(content) => match stage(&change.path, content) {
+                Ok(temp) => Some(temp),
+                Err(e) => {
+                    remove_staged(staged.into_iter().flatten());
+                    return Err(e);
+                }
+            },
+            None => None,
+        };
+        staged.push(temp);
+    }
+
+    let mut written: Vec<&Path> = Vec::new();
+    let mut staged = staged.into_iter();
+    for (change, temp) in changes.iter().zip(staged.by_ref()) {
+        let result = match temp {
+            Some(temp) => std::fs::rename(&temp, &change.path)
+                .with_context(|| format!("Failed to write {}", change.path.display())),
+            None if change.path.exists() => std::fs::remove_file(&change.path)
+                .with_context(|| format!("Failed to delete {}", change.path.display())),
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            remove_staged(staged.flatten());
+            let written = written
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>();
+            return Err(match written.is_empty() {
+                true => e.context("No file was changed"),
+                false => e.context(format!("Changed only {}", written.join(", "))),
+            });
+        }
+        written.push(&change.path);
+    }
+    Ok(())
+}
+
+fn stage(path: &Path, content: &str) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Invalid file path {}", path.display()))?;
+    let temp = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(&temp, content)
+        .with_context(|| format!("Failed to write {}", temp.display()))?;
+    Ok(temp)
+}
+
+fn remove_staged(temps: impl Iterator<Item = PathBuf>) {
+    for temp in temps {
+        if let Err(e) = std::fs::remove_file(&temp) {
+            tracing::error!("Failed to remove {}: {}", temp.display(), e);
+        }
+    }
+}
+
+pub fn format_changes(changes: &[FileChange]) -> String {
+    changes
+        .iter()
+        .map(|change| {
+            unified_diff(
+                &change.path,
+                change.original.as_deref(),
+                change.new.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn apply_text_edits(text: &str, edits: &[TextEdit]) -> Result<String> {
+    let mut offsets = edits
+        .iter()
+        .enumerate()
+        .map(|(index, edit)| {
+            let start = position_to_offset(text, edit.range.start);
+            let end = position_to_offset(text, edit.range.end);
+            if start > end {
+                bail!("Invalid edit range {:?}", edit.range);
+            }
+            Ok((start, end, index))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    offsets.sort_by_key(|(start, _, index)| (*start, *index));
+
+    let mut result = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for (start, end, index) in offsets {
+        if start < cursor {
+            bail!("Overlapping edits at {:?}", edits[index].range);
+        }
+        result.push_str(&text[cursor..start]);
+        result.push_str(&edits[index].new_text);
+        cursor = end;
+    }
+    result.push_str(&text[cursor..]);
+    Ok(result)
+}
+
+pub fn position_to_offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(index) => line_start += index + 1,
+            None => return text.len(),
+        }
+    }
+    let line_end = text[line_start..]
+        .find('\n')
+        .map(|index| line_start + index)
+        .unwrap_or(text.len());
+    let line = text[line_start..line_end].trim_end_matches('\r');
+
+    let mut utf16_offset = 0;
+    for (byte_offset, char) in line.char_indices() {
+        if utf16_offset >= position.character as usize {
+            return line_start + byte_offset;
+        }
+        utf16_offset += char.len_utf16();
+    }
+    line_start + line.len()
+}
+
+pub fn offset_to_position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+    Position::new(
+        before.matches('\n').count() as u32,
+        before[line_start..].encode_utf16().count() as u32,
+    )
+}
+
+pub fn unified_diff(path: &Path, original: Option<&str>, new: Option<&str>) -> String {
+    let old_lines: Vec<&str> = original
+        .map(|text| text.lines().collect())
+        .unwrap_or_default();
+    let new_lines: Vec<&str> = new.map(|text| text.lines().collect()).unwrap_or_default();
+    let old_name = match original {
+        Some(_) => path.display().to_string(),
+        None => "/dev/null".to_string(),
+    };
+    let new_name = match new {
+        Some(_) => path.display().to_string(),
+        None => "/dev/null".to_string(),
+    };
+
+    let mut output = format!("--- {old_name}\n+++ {new_name}\n");
+    let ops = diff_lines(&old_lines, &new_lines);
+    for hunk in hunks(&ops) {
+        let (mut old_start, mut new_start, mut old_count, mut new_count) = (0, 0, 0, 0);
+        let mut body = String::new();
+        for (position, op) in ops[hunk].iter().enumerate() {
+            let (old_index, new_index) = op.indices();
+            if position == 0 {
+                old_start = old_index;
+                new_start = new_index;
+            }
+            match op {
+                DiffOp::Equal(old, _) => {
+                    old_count += 1;
+                    new_count += 1;
+                    body.push_str(&format!(" {}\n", old_lines[*old]));
+                }
+                DiffOp::Delete(old, _) => {
+                    old_count += 1;
+                    body.push_str(&format!("-{}\n", old_lines[*old]));
+                }
+                DiffOp::Insert(_, new) => {
+                    new_count += 1;
+                    body.push_str(&format!("+{}\n", new_lines[*new]));
+                }
+            }
+        }
+        let old_start = if old_count == 0 {
+            old_start
+        } else {
+            old_start + 1
+        };
+        let new_start = if new_count == 0 {
+            new_start
+        } else {
+            new_start + 1
+        };
+        output.push_str(&format!(
+            "@@ -{old_start},{old_count} +{new_start},{new_count} @@\n{body}"
+        ));
+    }
+    output
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DiffOp {
+    Equal(usize, usize),
+    Delete(usize, usize),
+    Insert(usize, usize),
+}
+
+impl DiffOp {
+    fn indices(&self) -> (usize, usize) {
+        match *self {
+            DiffOp::Equal(old, new) | DiffOp::Delete(old, new) | DiffOp::Insert(old, new) => {
+                (old, new)
+            }
+        }
+    }
+}
+
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    // Myers' diff needs linear space, past the deadline it settles for a coarser diff
+    let deadline = Instant::now() + DIFF_TIMEOUT;
+    let mut ops = Vec::new();
+    for op in similar::capture_diff_slices_deadline(Algorithm::Myers, old, new, Some(deadline)) {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let (old_end, new_start) = (old_range.end, new_range.start);
+        match tag {
+            DiffTag::Equal => {
+                ops.extend(
+                    old_range
+                        .zip(new_range)
+                        .map(|(old, new)| DiffOp::Equal(old, new)),
+                );
+            }
+            // Replaced lines are shown as deleted, then inserted
+            DiffTag::Delete | DiffTag::Insert | DiffTag::Replace => {
+                ops.extend(old_range.map(|old| DiffOp::Delete(old, new_start)));
+                ops.extend(new_range.map(|new| DiffOp::Insert(old_end, new)));
+            }
+        }
+    }
+    ops
+}
+
+fn hunks(ops: &[DiffOp]) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+    for (index, op) in ops.iter().enumerate() {
+        if matches!(op, DiffOp::Equal(..)) {
+            continue;
+        }
+        let start = index.saturating_sub(DIFF_CONTEXT);
+        let end = (index + DIFF_CONTEXT + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if last.end >= start => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+    hunks
+}
+
+fn text_edit(edit: &OneOf<TextEdit, AnnotatedTextEdit>) -> TextEdit {
+    match edit {
+        OneOf::Left(edit) => edit.clone(),
+        OneOf::Right(edit) => edit.text_edit.clone(),
+    }
+}
+
+fn file_change<'a>(changes: &'a mut Vec<FileChange>, uri: &Url) -> Result<&'a mut FileChange> {
+    let path = uri
+        .to_file_path()
+        .map_err(|_| anyhow::anyhow!("Invalid file uri {uri}"))?;
+    let index = match changes.iter().position(|change| change.path == path) {
+        Some(index) => index,
+        None => {
+            let original = std::fs::read_to_string(&path).ok();
+            changes.push(FileChange {
+                path,
+                new: original.clone(),
+                original,
+            });
+            changes.len() - 1
+        }
+    };
+    Ok(&mut changes[index])
+}
+
+fn apply_to_file(changes: &mut Vec<FileChange>, uri: &Url, edits: &[TextEdit]) -> Result<()> {
+    let change = file_change(changes, uri)?;
+    let content = change
+        .new
+        .as_deref()
+        .with_context(|| format!("Cannot edit missing file {uri}"))?;
+    change.new = Some(apply_text_edits(content, edits)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::Range;
+
+    fn edit(start: (u32, u32), end: (u32, u32), new_text: &str) -> TextEdit {
+        TextEdit {
+            range: Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1)),
+            new_text: new_text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_text_edits() {
+        let text = "fn main() {\n    let ä = \"😀\";\n}\n";
+        let edits = vec![
+            edit((1, 15), (1, 15), "!"),
+            edit((1, 8), (1, 9), "a"),
+            edit((0, 3), (0, 7), "start"),
+        ];
+        assert_eq!(
+            apply_text_edits(text, &edits).unwrap(),
+            "fn start() {\n    let a = \"😀!\";\n}\n"
+        );
+        assert!(
+            apply_text_edits(text, &[edit((0, 0), (0, 4), ""), edit((0, 2), (0, 3), "")]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_write_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("lib.rs");
+        std::fs::write(&file, "old").unwrap();
+        let change = |path: PathBuf, new: &str| FileChange {
+            original: std::fs::read_to_string(&path).ok(),
+            path,
+            new: Some(new.to_string()),
+        };
+
+        // The second file can not be created below a file, so the first one stays untouched
+        let changes = vec![
+            change(file.clone(), "new"),
+            change(file.join("main.rs"), "fn main() {}"),
+        ];
+        assert!(write_changes(&changes).is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "old");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let module = dir.path().join("src/module.rs");
+        write_changes(&[
+            change(file.clone(), "new"),
+            change(module.clone(), "mod a;"),
+        ])
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "new");
+        assert_eq!(std::fs::read_to_string(&module).unwrap(), "mod a;");
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\nj\nk\n";
+        assert_eq!(
+            unified_diff(Path::new("/src/lib.rs"), Some(old), Some(new)),
+            "--- /src/lib.rs\n+++ /src/lib.rs\n@@ -2,9 +2,10 @@\n b\n c\n d\n-e\n+E\n f\n g\n h\n i\n j\n+k\n"
+        );
+        assert_eq!(
+            unified_diff(Path::new("/src/new.rs"), None, Some("x\n")),
+            "--- /dev/null\n+++ /src/new.rs\n@@ -0,0 +1,1 @@\n+x\n"
+        );
+
+        // Large files are diffed in linear space
+        let old = (0..20_000).map(|i| format!("{i}\n")).collect::<String>();
+        let new = old.replacen("10000\n", "ten thousand\n", 1);
+        assert_eq!(
+            unified_diff(Path::new("/src/lib.rs"), Some(&old), Some(&new))
+                .lines()
+                .filter(|line| line.starts_with(['-', '+']))
+                .collect::<Vec<_>>(),
+            vec![
+                "--- /src/lib.rs",
+                "+++ /src/lib.rs",
+                "-10000",
+                "+ten thousand"
+            ]
+        );
+    }
+}
@@ -38,6 +38,9 @@ async fn main() -> Result<()> {
     let (sender, receiver) = flume::unbounded();
     let context = ContextType::new(4000, sender).await;
     context.load_config().await?;
+    if let Err(e) = context.watch_config() {
+        error!("Failed to watch the configuration file: {}", e);
+    }
 
     let final_context = context.clone();
 
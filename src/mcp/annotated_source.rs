@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use crate::{
+    context::{Context, ProjectContext},
+    lsp::apply_text_edits,
+};
+use anyhow::Result;
+use lsp_types::{InlayHint, InlayHintLabel, Position, Range, TextEdit};
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, error_response, format_numbered_lines, get_info_from_request,
+        read_content, when_indexed, with_content,
+    },
+};
+
+pub struct AnnotatedSource;
+
+impl AnnotatedSource {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "annotated_source".to_string(),
+            description: Some("Get the source of a file or a line range with the inlay hints of rust-analyzer inlined as comments: the inferred type of every binding, parameter names at call sites and the types of method chains. Use this instead of many hover requests to see all types at once.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file"
+                    },
+                    "line": {
+                        "type": "number",
+                        "description": "The first line to return (1 based). Default is the start of the file."
+                    },
+                    "end_line": {
+                        "type": "number",
+                        "description": "The last line to return (1 based). Default is the end of the file."
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Optional unsaved content of the file to use instead of the file on disk"
+                    }
+                },
+                "required": ["file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let content = request.get_content();
+    with_content(
+        &project,
+        relative_file,
+        content,
+        annotate_source(&project, relative_file, request),
+    )
+    .await?
+}
+
+async fn annotate_source(
+    project: &Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let arguments = request.arguments.as_ref();
+    let content = read_content(project, relative_file, request.get_content().as_deref())?;
+    let line_count = content.lines().count() as u32;
+    let start_line = arguments
+        .and_then(|args| args.get("line"))
+        .and_then(|v| v.as_u64())
+        .map(|line| line.saturating_sub(1) as u32)
+        .unwrap_or(0);
+    let end_line = arguments
+        .and_then(|args| args.get("end_line"))
+        .and_then(|v| v.as_u64())
+        .map(|line| line.saturating_sub(1) as u32)
+        .unwrap_or(line_count.saturating_sub(1))
+        .max(start_line);
+    if start_line >= line_count {
+        return Err(error_response(&format!(
+            "Line {} not found in file",
+            start_line + 1
+        )));
+    }
+
+    let hints = project
+        .lsp
+        .inlay_hints(
+            relative_file,
+            Range::new(Position::new(start_line, 0), Position::new(end_line + 1, 0)),
+        )
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+        .unwrap_or_default();
+
+    let edits = hints
+        .iter()
+        .map(|hint| TextEdit {
+            range: Range::new(hint.position, hint.position),
+            new_text: format_hint(hint),
+        })
+        .collect::<Vec<_>>();
+    let annotated =
+        apply_text_edits(&content, &edits).map_err(|e| error_response(&format!("{e:#}")))?;
+    let Some(lines) = format_numbered_lines(&annotated, start_line, end_line, 0) else {
+        return Err(error_response("No source found"));
+    };
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: format!("```rust\n{lines}\n```"),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+fn format_hint(hint: &InlayHint) -> String {
+    let label = match &hint.label {
+        InlayHintLabel::String(label) => label.clone(),
+        InlayHintLabel::LabelParts(parts) => parts.iter().map(|part| part.value.as_str()).collect(),
+    };
+    let left = if hint.padding_left == Some(true) {
+        " "
+    } else {
+        ""
+    };
+    let right = if hint.padding_right == Some(true) {
+        " "
+    } else {
+        ""
+    };
+    format!("{left}/*{}*/{right}", label.trim())
+}
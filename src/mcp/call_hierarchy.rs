@@ -0,0 +1,251 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::context::{Context, ProjectContext};
+use anyhow::Result;
+use lsp_types::CallHierarchyItem;
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, error_response, find_symbol_position_on_line, get_info_from_request,
+        when_indexed,
+    },
+};
+
+const MAX_DEPTH: u64 = 5;
+const MAX_ENTRIES: usize = 200;
+
+pub struct CallHierarchy;
+
+impl CallHierarchy {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "call_hierarchy".to_string(),
+            description: Some("Get the call hierarchy of a function: who calls it (incoming) and what it calls (outgoing). Walks the hierarchy up to `depth` levels and returns the name, file and line (1 based) of every caller or callee as an indented tree.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "line": {
+                        "type": "number",
+                        "description": "The line number of the function in the file (1 based)"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "The name of the function to get the call hierarchy for"
+                    },
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file containing the function"
+                    },
+                    "direction": {
+                        "type": "string",
+                        "enum": ["incoming", "outgoing", "both"],
+                        "description": "Whether to list the callers (incoming), the callees (outgoing) or both. Default is incoming."
+                    },
+                    "depth": {
+                        "type": "number",
+                        "description": "How many levels of the hierarchy to walk (1 to 5). Default is 1."
+                    }
+                },
+                "required": ["line", "symbol", "file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let symbol = request.get_symbol()?;
+    let direction = request
+        .arguments
+        .as_ref()
+        .and_then(|args| args.get("direction"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("incoming");
+    let depth = request
+        .arguments
+        .as_ref()
+        .and_then(|args| args.get("depth"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1)
+        .clamp(1, MAX_DEPTH) as usize;
+
+    let position =
+        find_symbol_position_on_line(project.project.root().join(relative_file), &symbol, line)
+            .map_err(|e| error_response(&e))?;
+
+    let Some(items) = project
+        .lsp
+        .prepare_call_hierarchy(relative_file, position)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+    else {
+        return Err(error_response("No call hierarchy found"));
+    };
+
+    let mut sections = Vec::new();
+    for item in items {
+        if matches!(direction, "incoming" | "both") {
+            let tree = walk_hierarchy(&project, item.clone(), true, depth).await?;
+            sections.push(format!("## Incoming calls\n{tree}"));
+        }
+        if matches!(direction, "outgoing" | "both") {
+            let tree = walk_hierarchy(&project, item, false, depth).await?;
+            sections.push(format!("## Outgoing calls\n{tree}"));
+        }
+    }
+
+    if sections.is_empty() {
+        return Err(error_response(&format!(
+            "No call hierarchy found for direction {direction}"
+        )));
+    }
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: sections.join("\n"),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+async fn walk_hierarchy(
+    project: &Arc<ProjectContext>,
+    root: CallHierarchyItem,
+    incoming: bool,
+    depth: usize,
+) -> Result<String, CallToolResponse> {
+    let mut entries = Vec::new();
+    let mut listed = HashSet::new();
+    let mut stack: Vec<(CallHierarchyItem, Vec<u32>, usize)> = vec![(root, Vec::new(), 0)];
+
+    while let Some((item, call_lines, level)) = stack.pop() {
+        if entries.len() >= MAX_ENTRIES {
+            entries.push("... (truncated)".to_string());
+            break;
+        }
+        let is_listed = !listed.insert((item.uri.clone(), item.selection_range.start));
+        entries.push(format_item(&item, &call_lines, level, incoming, is_listed));
+        if is_listed || level >= depth {
+            continue;
+        }
+
+        let calls = if incoming {
+            project
+                .lsp
+                .incoming_calls(item)
+                .await
+                .map_err(|e| error_response(&format!("{e:#}")))?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|call| (call.from, call.from_ranges))
+                .collect::<Vec<_>>()
+        } else {
+            project
+                .lsp
+                .outgoing_calls(item)
+                .await
+                .map_err(|e| error_response(&format!("{e:#}")))?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|call| (call.to, call.from_ranges))
+                .collect::<Vec<_>>()
+        };
+
+        for (call, ranges) in calls.into_iter().rev() {
+            let call_lines = ranges.iter().map(|r| r.start.line + 1).collect();
+            stack.push((call, call_lines, level + 1));
+        }
+    }
+
+    Ok(entries.join("\n"))
+}
+
+fn format_item(
+    item: &CallHierarchyItem,
+    call_lines: &[u32],
+    level: usize,
+    incoming: bool,
+    is_listed: bool,
+) -> String {
+    let path = item
+        .uri
+        .to_file_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| item.uri.to_string());
+    let call_sites = if call_lines.is_empty() {
+        String::new()
+    } else {
+        let lines = call_lines
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if incoming {
+            format!(" (calls on lines {lines})")
+        } else {
+            format!(" (called on lines {lines} of the caller)")
+        }
+    };
+    let listed = if is_listed { " (listed above)" } else { "" };
+    format!(
+        "{}- {} {}:{}{}{}",
+        "  ".repeat(level),
+        item.name,
+        path,
+        item.selection_range.start.line + 1,
+        call_sites,
+        listed
+    )
+}
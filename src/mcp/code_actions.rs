@@ -0,0 +1,281 @@
+use std::sync::Arc;
+
+use crate::{
+    context::{Context, ProjectContext},
+    lsp::{format_changes, resolve_workspace_edit, write_changes},
+};
+use anyhow::Result;
+use lsp_types::{CodeActionOrCommand, Position, Range};
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::{Value, json};
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, ensure_indexed, error_response, find_symbol_position_on_line,
+        get_info_from_request, when_indexed,
+    },
+};
+
+pub struct CodeActions;
+
+impl CodeActions {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "code_actions".to_string(),
+            description: Some("List the code actions (assists and quick fixes) rust-analyzer offers for a range in a file, e.g. fill match arms, add missing impl members, extract function or import a missing path. Select an action by its number or title to get its changes as a unified diff; the changes are only written to disk if `apply` is true.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file"
+                    },
+                    "line": {
+                        "type": "number",
+                        "description": "The first line of the range (1 based)"
+                    },
+                    "end_line": {
+                        "type": "number",
+                        "description": "The last line of the range (1 based). Default is `line`."
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "Optional symbol on `line` to place the cursor on instead of selecting whole lines. Many assists (e.g. fill match arms) depend on the cursor position."
+                    },
+                    "action": {
+                        "type": ["number", "string"],
+                        "description": "The number (1 based) or the title of the action to resolve. If omitted, the available actions are listed."
+                    },
+                    "apply": {
+                        "type": "boolean",
+                        "description": "If true, the changes of the selected action are written to disk. Default is false."
+                    }
+                },
+                "required": ["file", "line"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let arguments = request.arguments.as_ref();
+    let end_line = arguments
+        .and_then(|args| args.get("end_line"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(line)
+        .max(line);
+    let symbol = arguments
+        .and_then(|args| args.get("symbol"))
+        .and_then(|v| v.as_str());
+    let selected = arguments.and_then(|args| args.get("action"));
+    let apply = arguments
+        .and_then(|args| args.get("apply"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    // Changes computed while indexing can be incomplete, even if it ended in the meantime
+    if apply {
+        ensure_indexed(&project)?;
+    }
+
+    let absolute_file = project.project.root().join(relative_file);
+    let range = match symbol {
+        Some(symbol) => {
+            let start = find_symbol_position_on_line(&absolute_file, symbol, line)
+                .map_err(|e| error_response(&e))?;
+            let name = symbol.rsplit("::").next().unwrap_or(symbol);
+            Range::new(
+                start,
+                Position::new(
+                    start.line,
+                    start.character + name.encode_utf16().count() as u32,
+                ),
+            )
+        }
+        None => {
+            let content = std::fs::read_to_string(&absolute_file)
+                .map_err(|e| error_response(&format!("{e:#}")))?;
+            let end_index = end_line.saturating_sub(1) as usize;
+            let Some(last_line) = content.lines().nth(end_index) else {
+                return Err(error_response(&format!(
+                    "Line {end_line} not found in file"
+                )));
+            };
+            Range::new(
+                Position::new(line.saturating_sub(1) as u32, 0),
+                Position::new(end_index as u32, last_line.encode_utf16().count() as u32),
+            )
+        }
+    };
+
+    let diagnostics = project
+        .diagnostics
+        .file(&absolute_file)
+        .into_iter()
+        .filter(|d| d.range.start.line <= range.end.line && d.range.end.line >= range.start.line)
+        .collect();
+
+    let actions = project
+        .lsp
+        .code_actions(relative_file, range, diagnostics)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+        .unwrap_or_default();
+    if actions.is_empty() {
+        return Err(error_response("No code actions available for this range"));
+    }
+
+    let Some(selected) = selected else {
+        let text = actions
+            .iter()
+            .enumerate()
+            .map(|(index, action)| format!("{}. {}", index + 1, format_action(action)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Ok(CallToolResponse {
+            content: vec![ToolResponseContent::Text { text }],
+            is_error: None,
+            meta: None,
+        });
+    };
+
+    let action = match selected {
+        Value::Number(number) => number
+            .as_u64()
+            .and_then(|index| actions.get((index as usize).checked_sub(1)?)),
+        Value::String(title) => actions
+            .iter()
+            .find(|action| action_title(action).eq_ignore_ascii_case(title)),
+        _ => None,
+    };
+    let Some(action) = action else {
+        return Err(error_response(&format!("Code action {selected} not found")));
+    };
+    let CodeActionOrCommand::CodeAction(action) = action.clone() else {
+        return Err(error_response(
+            "The selected action is a command and can not be resolved into an edit",
+        ));
+    };
+    if let Some(disabled) = &action.disabled {
+        return Err(error_response(&format!(
+            "The selected action is disabled: {}",
+            disabled.reason
+        )));
+    }
+
+    let title = action.title.clone();
+    let action = if action.edit.is_none() && action.data.is_some() {
+        project
+            .lsp
+            .resolve_code_action(action)
+            .await
+            .map_err(|e| error_response(&format!("{e:#}")))?
+    } else {
+        action
+    };
+    let Some(edit) = action.edit else {
+        return Err(error_response(&format!(
+            "Code action `{title}` has no edit"
+        )));
+    };
+
+    let changes = resolve_workspace_edit(&edit).map_err(|e| error_response(&format!("{e:#}")))?;
+    if changes.is_empty() {
+        return Err(error_response(&format!(
+            "Code action `{title}` changes nothing"
+        )));
+    }
+    let diff = format_changes(&changes);
+    let text = if apply {
+        // Indexing could also have started again, e.g. because a `Cargo.toml` changed
+        ensure_indexed(&project)?;
+        write_changes(&changes).map_err(|e| error_response(&format!("{e:#}")))?;
+        format!("Applied `{title}`:\n```diff\n{diff}```")
+    } else {
+        format!(
+            "Changes of `{title}` (not applied, call again with `apply: true` to write them to disk):\n```diff\n{diff}```"
+        )
+    };
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text { text }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+fn action_title(action: &CodeActionOrCommand) -> &str {
+    match action {
+        CodeActionOrCommand::CodeAction(action) => &action.title,
+        CodeActionOrCommand::Command(command) => &command.title,
+    }
+}
+
+fn format_action(action: &CodeActionOrCommand) -> String {
+    match action {
+        CodeActionOrCommand::CodeAction(action) => {
+            let kind = action
+                .kind
+                .as_ref()
+                .map(|kind| format!("[{}] ", kind.as_str()))
+                .unwrap_or_default();
+            let disabled = action
+                .disabled
+                .as_ref()
+                .map(|disabled| format!(" (disabled: {})", disabled.reason))
+                .unwrap_or_default();
+            format!("{kind}{}{disabled}", action.title)
+        }
+        CodeActionOrCommand::Command(command) => format!("[command] {}", command.title),
+    }
+}
@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use crate::context::{Context, ProjectContext};
+use anyhow::Result;
+use futures::future::join_all;
+use lsp_types::{CompletionItem, CompletionResponse, Documentation, Position};
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, error_response, get_info_from_request, kind_name, read_content,
+        when_indexed, with_content,
+    },
+};
+
+pub struct Completions;
+
+impl Completions {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "completions".to_string(),
+            description: Some("Get the completions rust-analyzer offers at a position, ranked by relevance, with their signature, documentation and the import each one needs. Pass `line_content` to complete a hypothetical version of the line without changing the file on disk, e.g. `    let len = items.` to list the methods of `items`.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file"
+                    },
+                    "line": {
+                        "type": "number",
+                        "description": "The line number to complete on (1 based)"
+                    },
+                    "column": {
+                        "type": "number",
+                        "description": "The column of the cursor (1 based). Default is the end of the line."
+                    },
+                    "line_content": {
+                        "type": "string",
+                        "description": "Optional text that replaces the line before completing. The file on disk is not changed."
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Optional unsaved content of the file to use instead of the file on disk"
+                    },
+                    "limit": {
+                        "type": "number",
+                        "description": "The maximum number of completions to return. Default is 30."
+                    }
+                },
+                "required": ["file", "line"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let arguments = request.arguments.as_ref();
+    let column = arguments
+        .and_then(|args| args.get("column"))
+        .and_then(|v| v.as_u64());
+    let line_content = arguments
+        .and_then(|args| args.get("line_content"))
+        .and_then(|v| v.as_str());
+    let limit = arguments
+        .and_then(|args| args.get("limit"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(30) as usize;
+
+    let content = request.get_content();
+    let file_content = read_content(&project, relative_file, content.as_deref())?;
+    let index = line.saturating_sub(1) as usize;
+    let mut lines = file_content.lines().collect::<Vec<_>>();
+    if index >= lines.len() {
+        return Err(error_response(&format!("Line {line} not found in file")));
+    }
+    if let Some(line_content) = line_content {
+        lines[index] = line_content;
+    }
+    let text = lines[index];
+    let byte_column = column
+        .and_then(|column| text.char_indices().nth(column.saturating_sub(1) as usize))
+        .map(|(offset, _)| offset)
+        .unwrap_or(text.len());
+    let position = Position::new(
+        index as u32,
+        text[..byte_column].encode_utf16().count() as u32,
+    );
+
+    let buffer = if content.is_some() || line_content.is_some() {
+        let mut buffer = lines.join("\n");
+        buffer.push('\n');
+        Some(buffer)
+    } else {
+        None
+    };
+    with_content(
+        &project,
+        relative_file,
+        buffer,
+        complete(&project, relative_file, position, limit),
+    )
+    .await?
+}
+
+async fn complete(
+    project: &Arc<ProjectContext>,
+    relative_file: &str,
+    position: Position,
+    limit: usize,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let response = project
+        .lsp
+        .completion(relative_file, position)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?;
+
+    let (mut items, is_incomplete) = match response {
+        Some(CompletionResponse::Array(items)) => (items, false),
+        Some(CompletionResponse::List(list)) => (list.items, list.is_incomplete),
+        None => (Vec::new(), false),
+    };
+    if items.is_empty() {
+        return Err(error_response("No completions found at this position"));
+    }
+    items.sort_by(|a, b| {
+        let a_key = a.sort_text.as_ref().unwrap_or(&a.label);
+        let b_key = b.sort_text.as_ref().unwrap_or(&b.label);
+        a_key.cmp(b_key)
+    });
+    let total = items.len();
+
+    let items = join_all(items.into_iter().take(limit).map(|item| async move {
+        if item.additional_text_edits.is_none() && item.data.is_some() {
+            project
+                .lsp
+                .resolve_completion_item(item.clone())
+                .await
+                .unwrap_or(item)
+        } else {
+            item
+        }
+    }))
+    .await;
+    let mut contents = items
+        .iter()
+        .enumerate()
+        .map(|(rank, item)| format!("{}. {}", rank + 1, format_item(item)))
+        .collect::<Vec<_>>();
+    if total > limit {
+        contents.push(format!("... ({} more)", total - limit));
+    } else if is_incomplete {
+        contents.push("... (incomplete, type more characters to narrow it down)".to_string());
+    }
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: contents.join("\n"),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+fn format_item(item: &CompletionItem) -> String {
+    let kind = item
+        .kind
+        .map(|kind| format!(" ({})", kind_name(kind)))
+        .unwrap_or_default();
+    let detail = item
+        .detail
+        .as_ref()
+        .map(|detail| format!(" `{detail}`"))
+        .unwrap_or_default();
+    let imports = item
+        .additional_text_edits
+        .iter()
+        .flatten()
+        .flat_map(|edit| edit.new_text.lines())
+        .map(str::trim)
+        .filter(|line| line.starts_with("use "))
+        .collect::<Vec<_>>();
+    let import = if imports.is_empty() {
+        String::new()
+    } else {
+        format!(", needs `{}`", imports.join(" "))
+    };
+    let documentation = item
+        .documentation
+        .as_ref()
+        .map(|documentation| match documentation {
+            Documentation::String(text) => text.as_str(),
+            Documentation::MarkupContent(content) => content.value.as_str(),
+        })
+        .and_then(|text| text.split("\n\n").next())
+        .map(|summary| format!("\n   {}", summary.replace('\n', " ")))
+        .unwrap_or_default();
+    format!("`{}`{kind}{detail}{import}{documentation}", item.label)
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use crate::context::{Context, ProjectContext};
+use anyhow::Result;
+use lazy_static::lazy_static;
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use regex::Regex;
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{error_response, get_info_from_request, when_indexed},
+};
+
+pub struct CrateGraph;
+
+impl CrateGraph {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "crate_graph".to_string(),
+            description: Some("Get the crate dependency graph rust-analyzer resolved for the project, as a plain adjacency list (every crate followed by the crates it depends on) and in the graphviz DOT format.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to any file in the project, e.g. its `Cargo.toml`"
+                    },
+                    "include_dependencies": {
+                        "type": "boolean",
+                        "description": "If true, the external dependencies and the standard library are included. Default is false, which only includes the crates of the workspace."
+                    }
+                },
+                "required": ["file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    _relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let include_dependencies = request
+        .arguments
+        .as_ref()
+        .and_then(|args| args.get("include_dependencies"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let dot = project
+        .lsp
+        .crate_graph(include_dependencies)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?;
+
+    let adjacency = parse_crate_graph(&dot)
+        .into_iter()
+        .map(|(name, dependencies)| {
+            if dependencies.is_empty() {
+                format!("- {name}")
+            } else {
+                let dependencies = dependencies.into_iter().collect::<Vec<_>>().join(", ");
+                format!("- {name} -> {dependencies}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: format!(
+                "## Adjacency list\n{adjacency}\n\n## DOT\n```dot\n{}\n```",
+                dot.trim_end()
+            ),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+lazy_static! {
+    static ref NODE_RE: Regex = Regex::new(r#"^\s*(\w+)\s*\[label="([^"]*)"\]"#).unwrap();
+    static ref EDGE_RE: Regex = Regex::new(r"^\s*(\w+)\s*->\s*(\w+)").unwrap();
+}
+
+/// Maps every crate to its dependencies. Crates are named by their label, followed by their
+/// DOT node id if several crates share it, e.g. two versions of a dependency.
+fn parse_crate_graph(dot: &str) -> BTreeMap<String, BTreeSet<String>> {
+    let mut labels = BTreeMap::new();
+    let mut edges = Vec::new();
+    for line in dot.lines() {
+        if let Some(captures) = EDGE_RE.captures(line) {
+            edges.push((captures[1].to_string(), captures[2].to_string()));
+        } else if let Some(captures) = NODE_RE.captures(line) {
+            labels.insert(captures[1].to_string(), captures[2].to_string());
+        }
+    }
+
+    let mut label_counts = BTreeMap::new();
+    for label in labels.values() {
+        *label_counts.entry(label.as_str()).or_insert(0) += 1;
+    }
+    let name = |id: &str| match labels.get(id) {
+        Some(label) if label_counts[label.as_str()] > 1 => format!("{label} ({id})"),
+        Some(label) => label.clone(),
+        None => id.to_string(),
+    };
+
+    let mut graph = labels
+        .keys()
+        .map(|id| (id.clone(), BTreeSet::new()))
+        .collect::<BTreeMap<_, _>>();
+    for (from, to) in edges {
+        graph.entry(from).or_default().insert(to);
+    }
+    graph
+        .into_iter()
+        .map(|(id, dependencies)| {
+            let dependencies = dependencies.iter().map(|id| name(id)).collect();
+            (name(&id), dependencies)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_crate_graph() {
+        let dot = r#"digraph rust_analyzer_crate_graph {
+    _0[label="core"][shape="box"];
+    _1[label="alloc"][shape="box"];
+    _2[label="my_crate"][shape="box"];
+    _1 -> _0[label=""];
+    _2 -> _1[label=""];
+    _2 -> _0[label=""];
+}"#;
+        let graph = parse_crate_graph(dot);
+        assert_eq!(graph.len(), 3);
+        assert!(graph["core"].is_empty());
+        assert_eq!(
+            graph["my_crate"].iter().collect::<Vec<_>>(),
+            vec!["alloc", "core"]
+        );
+    }
+
+    #[test]
+    fn test_parse_crate_graph_with_same_names() {
+        let dot = r#"digraph rust_analyzer_crate_graph {
+    _0[label="rand"][shape="box"];
+    _1[label="rand"][shape="box"];
+    _2[label="my_crate"][shape="box"];
+    _2 -> _0[label=""];
+    _2 -> _1[label=""];
+    _1 -> _0[label=""];
+}"#;
+        let graph = parse_crate_graph(dot);
+        assert_eq!(graph.len(), 3);
+        assert!(graph["rand (_0)"].is_empty());
+        assert_eq!(
+            graph["rand (_1)"].iter().collect::<Vec<_>>(),
+            vec!["rand (_0)"]
+        );
+        assert_eq!(
+            graph["my_crate"].iter().collect::<Vec<_>>(),
+            vec!["rand (_0)", "rand (_1)"]
+        );
+    }
+}
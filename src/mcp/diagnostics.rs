@@ -0,0 +1,207 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use crate::context::{Context, ProjectContext};
+use anyhow::Result;
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{RequestExtension, error_response, get_info_from_request, when_indexed},
+};
+
+const OVERLAY_DIAGNOSTICS_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Diagnostics;
+
+impl Diagnostics {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "diagnostics".to_string(),
+            description: Some("Get the current errors and warnings rust-analyzer reported for a file or for the whole project. Returns instantly without running `cargo check`; the results reflect the last check rust-analyzer ran after a file was saved. While another call passes unsaved content of a file, the diagnostics of that file can reflect that content.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to a `.rs` file to get the diagnostics for, or to the `Cargo.toml` of the project to get the diagnostics of all files"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Optional unsaved content of the `.rs` file to check instead of the file on disk. Only the diagnostics of rust-analyzer itself are returned for it, not the ones of `cargo check`."
+                    },
+                    "only_errors": {
+                        "type": "boolean",
+                        "description": "If true, only errors will be returned. If false, errors and warnings will be returned. Default is false."
+                    }
+                },
+                "required": ["file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let only_errors = request
+        .arguments
+        .as_ref()
+        .and_then(|args| args.get("only_errors"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let absolute_file = project.project.root().join(relative_file);
+    let files = if absolute_file.extension().is_some_and(|ext| ext == "rs") {
+        let diagnostics = match request.get_content() {
+            Some(content) => {
+                overlay_diagnostics(&project, relative_file, &absolute_file, content).await?
+            }
+            None => project.diagnostics.file(&absolute_file),
+        };
+        vec![(absolute_file, diagnostics)]
+    } else {
+        project.diagnostics.all()
+    };
+
+    let contents = files
+        .iter()
+        .filter_map(|(path, diagnostics)| format_file_diagnostics(path, diagnostics, only_errors))
+        .collect::<Vec<_>>();
+
+    let text = if contents.is_empty() {
+        "No diagnostics".to_string()
+    } else {
+        contents.join("\n")
+    };
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text { text }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+async fn overlay_diagnostics(
+    project: &ProjectContext,
+    relative_file: &str,
+    absolute_file: &Path,
+    content: String,
+) -> Result<Vec<Diagnostic>, CallToolResponse> {
+    let overlay = project
+        .lsp
+        .open_overlay(relative_file, content)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?;
+    let published = project
+        .diagnostics
+        .wait_for_version(
+            absolute_file,
+            overlay.version(),
+            OVERLAY_DIAGNOSTICS_TIMEOUT,
+        )
+        .await;
+    let diagnostics = project.diagnostics.file(absolute_file);
+    drop(overlay);
+    if !published {
+        return Err(error_response(
+            "Timed out waiting for rust-analyzer to publish diagnostics for the content",
+        ));
+    }
+    Ok(diagnostics)
+}
+
+fn format_file_diagnostics(
+    path: &Path,
+    diagnostics: &[Diagnostic],
+    only_errors: bool,
+) -> Option<String> {
+    let lines = diagnostics
+        .iter()
+        .filter(|d| {
+            let severity = d.severity.unwrap_or(DiagnosticSeverity::ERROR);
+            severity == DiagnosticSeverity::ERROR
+                || (!only_errors && severity == DiagnosticSeverity::WARNING)
+        })
+        .map(format_diagnostic)
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        return None;
+    }
+    Some(format!("## {}\n{}", path.display(), lines.join("\n")))
+}
+
+fn format_diagnostic(diagnostic: &Diagnostic) -> String {
+    let severity = match diagnostic.severity {
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        _ => "error",
+    };
+    let code = match &diagnostic.code {
+        Some(NumberOrString::String(code)) => format!("[{code}]"),
+        Some(NumberOrString::Number(code)) => format!("[{code}]"),
+        None => String::new(),
+    };
+    let source = diagnostic
+        .source
+        .as_ref()
+        .map(|source| format!(" ({source})"))
+        .unwrap_or_default();
+    format!(
+        "- {}{} {}:{}: {}{}",
+        severity,
+        code,
+        diagnostic.range.start.line + 1,
+        diagnostic.range.start.character + 1,
+        diagnostic.message,
+        source
+    )
+}
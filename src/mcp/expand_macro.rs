@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use crate::context::{Context, ProjectContext};
+use anyhow::Result;
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, error_response, find_symbol_position_on_line, get_info_from_request,
+        when_indexed,
+    },
+};
+
+pub struct ExpandMacro;
+
+impl ExpandMacro {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "expand_macro".to_string(),
+            description: Some("Get the recursively expanded code of a macro invocation or a derive, e.g. `tokio::select!`, `vec!` or `#[derive(Serialize)]`. Use `derive` as the symbol to expand all derives of an item at once.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "line": {
+                        "type": "number",
+                        "description": "The line number of the macro invocation or derive in the file (1 based)"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "The name of the macro or derive to expand, e.g. `select!` or `Serialize`"
+                    },
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file containing the macro"
+                    }
+                },
+                "required": ["line", "symbol", "file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let symbol = request.get_symbol()?;
+    let name = symbol.trim_end_matches('!');
+
+    let position =
+        find_symbol_position_on_line(project.project.root().join(relative_file), name, line)
+            .map_err(|e| error_response(&e))?;
+
+    let Some(expanded) = project
+        .lsp
+        .expand_macro(relative_file, position)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+    else {
+        return Err(error_response(&format!(
+            "No macro to expand found at {symbol} on line {line}"
+        )));
+    };
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: format!(
+                "Expansion of `{}`:\n```rust\n{}\n```",
+                expanded.name, expanded.expansion
+            ),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
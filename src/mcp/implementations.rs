@@ -0,0 +1,195 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    context::{Context, ProjectContext},
+    lsp::get_locations,
+};
+use anyhow::Result;
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, error_response, find_enclosing_items, find_symbol_position_on_line,
+        get_info_from_request, get_numbered_file_lines, when_indexed,
+    },
+};
+
+pub struct Implementations;
+
+impl Implementations {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "implementations".to_string(),
+            description: Some("List every impl block for a trait or a type. For a trait this returns all types implementing it, for a type all inherent and trait impls. Every impl block is returned with its header including the generic bounds and its source.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "line": {
+                        "type": "number",
+                        "description": "The line number of the trait or type in the file (1 based)"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "The name of the trait or type to list the implementations for"
+                    },
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file containing the trait or type"
+                    },
+                    "headers_only": {
+                        "type": "boolean",
+                        "description": "If true, only the impl headers are returned without the source. Default is false."
+                    }
+                },
+                "required": ["line", "symbol", "file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let symbol = request.get_symbol()?;
+    let headers_only = request
+        .arguments
+        .as_ref()
+        .and_then(|args| args.get("headers_only"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let position =
+        find_symbol_position_on_line(project.project.root().join(relative_file), &symbol, line)
+            .map_err(|e| error_response(&e))?;
+
+    let Some(implementations) = project
+        .lsp
+        .implementations(relative_file, position)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+    else {
+        return Err(error_response("No implementations found"));
+    };
+
+    let mut known_items = HashSet::new();
+    let mut contents = Vec::new();
+    for location in get_locations(implementations) {
+        let Ok(path) = location.uri.to_file_path() else {
+            continue;
+        };
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let Some(range) = find_enclosing_items(&project, &path, location.range)
+            .await
+            .into_iter()
+            .next()
+        else {
+            continue;
+        };
+        if !known_items.insert((path.clone(), range.start.line)) {
+            continue;
+        }
+
+        let item = content
+            .lines()
+            .skip(range.start.line as usize)
+            .take((range.end.line - range.start.line + 1) as usize)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut entry = format!(
+            "## {}:{}-{}\n`{}`",
+            path.display(),
+            range.start.line + 1,
+            range.end.line + 1,
+            item_header(&item)
+        );
+        let source = if headers_only {
+            None
+        } else {
+            get_numbered_file_lines(&path, range.start.line, range.end.line, 0)
+                .ok()
+                .flatten()
+        };
+        if let Some(lines) = source {
+            entry.push_str(&format!("\n```rust\n{lines}\n```"));
+        }
+        contents.push(entry);
+    }
+
+    if contents.is_empty() {
+        return Err(error_response("No implementations found"));
+    }
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: contents.join("\n"),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+fn item_header(item: &str) -> String {
+    let header = item
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("#[") && !line.starts_with("//"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    header
+        .split('{')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
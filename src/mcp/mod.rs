@@ -1,11 +1,29 @@
+mod annotated_source;
+mod call_hierarchy;
 mod cargo_check;
 mod cargo_test;
+mod code_actions;
+mod completions;
 mod crate_docs;
+mod crate_graph;
+mod diagnostics;
+mod expand_macro;
+mod implementations;
+mod related_tests;
+mod rename_symbol;
+mod server_log;
+mod signature_help;
+mod ssr;
+mod symbol_definition;
 mod symbol_docs;
 mod symbol_impl;
 mod symbol_references;
 mod symbol_resolve;
+mod syntax_tree;
+mod type_layout;
 mod utils;
+mod view_hir;
+mod workspace_symbols;
 
 use std::path::PathBuf;
 
@@ -43,6 +61,10 @@ pub async fn run_server(context: Context) -> Result<()> {
             symbol_docs::SymbolDocs::tool(),
             symbol_docs::SymbolDocs::call(context.clone()),
         )
+        .register_tool(
+            symbol_definition::SymbolDefinition::tool(),
+            symbol_definition::SymbolDefinition::call(context.clone()),
+        )
         .register_tool(
             symbol_impl::SymbolImpl::tool(),
             symbol_impl::SymbolImpl::call(context.clone()),
@@ -55,10 +77,78 @@ pub async fn run_server(context: Context) -> Result<()> {
             symbol_resolve::SymbolResolve::tool(),
             symbol_resolve::SymbolResolve::call(context.clone()),
         )
+        .register_tool(
+            annotated_source::AnnotatedSource::tool(),
+            annotated_source::AnnotatedSource::call(context.clone()),
+        )
+        .register_tool(
+            completions::Completions::tool(),
+            completions::Completions::call(context.clone()),
+        )
+        .register_tool(
+            signature_help::SignatureHelp::tool(),
+            signature_help::SignatureHelp::call(context.clone()),
+        )
+        .register_tool(
+            call_hierarchy::CallHierarchy::tool(),
+            call_hierarchy::CallHierarchy::call(context.clone()),
+        )
+        .register_tool(
+            implementations::Implementations::tool(),
+            implementations::Implementations::call(context.clone()),
+        )
+        .register_tool(
+            expand_macro::ExpandMacro::tool(),
+            expand_macro::ExpandMacro::call(context.clone()),
+        )
+        .register_tool(
+            type_layout::TypeLayout::tool(),
+            type_layout::TypeLayout::call(context.clone()),
+        )
+        .register_tool(
+            syntax_tree::SyntaxTree::tool(),
+            syntax_tree::SyntaxTree::call(context.clone()),
+        )
+        .register_tool(
+            view_hir::ViewHir::tool(),
+            view_hir::ViewHir::call(context.clone()),
+        )
+        .register_tool(
+            workspace_symbols::WorkspaceSymbols::tool(),
+            workspace_symbols::WorkspaceSymbols::call(context.clone()),
+        )
         .register_tool(
             crate_docs::CrateDocs::tool(),
             crate_docs::CrateDocs::call(context.clone()),
         )
+        .register_tool(
+            rename_symbol::RenameSymbol::tool(),
+            rename_symbol::RenameSymbol::call(context.clone()),
+        )
+        .register_tool(
+            ssr::Ssr::tool(),
+            ssr::Ssr::call(context.clone()),
+        )
+        .register_tool(
+            code_actions::CodeActions::tool(),
+            code_actions::CodeActions::call(context.clone()),
+        )
+        .register_tool(
+            diagnostics::Diagnostics::tool(),
+            diagnostics::Diagnostics::call(context.clone()),
+        )
+        .register_tool(
+            crate_graph::CrateGraph::tool(),
+            crate_graph::CrateGraph::call(context.clone()),
+        )
+        .register_tool(
+            server_log::ServerLog::tool(),
+            server_log::ServerLog::call(context.clone()),
+        )
+        .register_tool(
+            related_tests::RelatedTests::tool(),
+            related_tests::RelatedTests::call(context.clone()),
+        )
         .register_tool(
             cargo_check::CargoCheck::tool(),
             cargo_check::CargoCheck::call(context.clone()),
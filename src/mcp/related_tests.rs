@@ -0,0 +1,224 @@
+use std::sync::Arc;
+
+use crate::{
+    context::{Context, ProjectContext},
+    lsp::Runnable,
+};
+use anyhow::Result;
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, error_response, find_symbol_position_on_line, get_info_from_request,
+        when_indexed,
+    },
+};
+
+const MAX_RUNS: usize = 10;
+
+pub struct RelatedTests;
+
+impl RelatedTests {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "related_tests".to_string(),
+            description: Some("List the tests that exercise a function or type, and the runnables (tests, test modules, binaries) at its position, each with the exact `cargo` arguments to run it. Optionally runs the tests and returns their output.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "line": {
+                        "type": "number",
+                        "description": "The line number of the symbol in the file (1 based)"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "The name of the function, type or test"
+                    },
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file containing the symbol"
+                    },
+                    "run": {
+                        "type": "boolean",
+                        "description": "If true, the listed tests are run with `cargo test` and their output is returned. Binaries and whole packages are only listed. Default is false."
+                    },
+                    "backtrace": {
+                        "type": "boolean",
+                        "description": "If true, failing tests that are run include a backtrace. Default is false."
+                    }
+                },
+                "required": ["line", "symbol", "file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let symbol = request.get_symbol()?;
+    let arguments = request.arguments.as_ref();
+    let run = arguments
+        .and_then(|args| args.get("run"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let backtrace = arguments
+        .and_then(|args| args.get("backtrace"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let position =
+        find_symbol_position_on_line(project.project.root().join(relative_file), &symbol, line)
+            .map_err(|e| error_response(&e))?;
+
+    let mut runnables = project
+        .lsp
+        .related_tests(relative_file, position)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?;
+    let related = runnables.len();
+    let at_position = project
+        .lsp
+        .runnables(relative_file, Some(position))
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?;
+    for runnable in at_position {
+        if !runnables.iter().any(|known| known.label == runnable.label) {
+            runnables.push(runnable);
+        }
+    }
+    if runnables.is_empty() {
+        return Err(error_response(&format!(
+            "No related tests or runnables found for {symbol}"
+        )));
+    }
+
+    let mut contents = runnables.iter().map(format_runnable).collect::<Vec<_>>();
+
+    if run {
+        // The runnables at the position also include binaries and whole packages, which are
+        // only listed
+        let tests = runnables.iter().enumerate().filter(|(index, runnable)| {
+            is_test(runnable) && (*index < related || runs_test(runnable, &symbol))
+        });
+        for (_, runnable) in tests.take(MAX_RUNS) {
+            let output = project
+                .cargo_remote
+                .test_with_args(
+                    &runnable.args.cargo_args,
+                    &runnable.args.executable_args,
+                    runnable.args.cwd.as_deref(),
+                    &runnable.args.environment,
+                    backtrace,
+                )
+                .await
+                .map_err(|e| error_response(&format!("{e:#}")))?;
+            contents.push(format!(
+                "## Output of {}\n{}",
+                runnable.label,
+                output.join("\n")
+            ));
+        }
+    }
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: contents.join("\n"),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+fn is_test(runnable: &Runnable) -> bool {
+    runnable.kind == "cargo" && runnable.args.cargo_args.first().map(String::as_str) == Some("test")
+}
+
+/// Whether the test filter of `runnable` names `symbol`, e.g. `tests::test_parse` for
+/// `test_parse`.
+fn runs_test(runnable: &Runnable, symbol: &str) -> bool {
+    let name = symbol.rsplit("::").next().unwrap_or(symbol);
+    runnable
+        .args
+        .executable_args
+        .first()
+        .is_some_and(|filter| filter.rsplit("::").next() == Some(name))
+}
+
+fn format_runnable(runnable: &Runnable) -> String {
+    let location = runnable
+        .location
+        .as_ref()
+        .map(|location| {
+            let path = location
+                .target_uri
+                .to_file_path()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|_| location.target_uri.to_string());
+            format!(
+                " {}:{}",
+                path,
+                location.target_selection_range.start.line + 1
+            )
+        })
+        .unwrap_or_default();
+    let mut command = runnable.args.cargo_args.join(" ");
+    if !runnable.args.executable_args.is_empty() {
+        command.push_str(" -- ");
+        command.push_str(&runnable.args.executable_args.join(" "));
+    }
+    if command.is_empty() {
+        format!("- {}{location}", runnable.label)
+    } else {
+        format!("- {}{location}: `cargo {command}`", runnable.label)
+    }
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use crate::{
+    context::{Context, ProjectContext},
+    lsp::{format_changes, resolve_workspace_edit, write_changes},
+};
+use anyhow::Result;
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, ensure_indexed, error_response, find_symbol_position_on_line,
+        get_info_from_request, when_indexed,
+    },
+};
+
+pub struct RenameSymbol;
+
+impl RenameSymbol {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "rename_symbol".to_string(),
+            description: Some("Rename a symbol and all its references across the project using rust-analyzer instead of a text search. Returns the changes of all affected files as a unified diff; the changes are only written to disk if `apply` is true.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "line": {
+                        "type": "number",
+                        "description": "The line number of the symbol in the file (1 based)"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "The current name of the symbol"
+                    },
+                    "new_name": {
+                        "type": "string",
+                        "description": "The new name of the symbol"
+                    },
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file containing the symbol"
+                    },
+                    "apply": {
+                        "type": "boolean",
+                        "description": "If true, the changes are written to disk. Default is false."
+                    }
+                },
+                "required": ["line", "symbol", "new_name", "file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let symbol = request.get_symbol()?;
+    let arguments = request.arguments.as_ref();
+    let new_name = arguments
+        .and_then(|args| args.get("new_name"))
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| error_response("New name is required"))?;
+    let apply = arguments
+        .and_then(|args| args.get("apply"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    // Changes computed while indexing can be incomplete, even if it ended in the meantime
+    if apply {
+        ensure_indexed(&project)?;
+    }
+
+    let position =
+        find_symbol_position_on_line(project.project.root().join(relative_file), &symbol, line)
+            .map_err(|e| error_response(&e))?;
+
+    if project
+        .lsp
+        .prepare_rename(relative_file, position)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+        .is_none()
+    {
+        return Err(error_response(&format!(
+            "Symbol {symbol} can not be renamed"
+        )));
+    }
+
+    let Some(edit) = project
+        .lsp
+        .rename(relative_file, position, new_name)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+    else {
+        return Err(error_response(&format!(
+            "Symbol {symbol} can not be renamed"
+        )));
+    };
+
+    let changes = resolve_workspace_edit(&edit).map_err(|e| error_response(&format!("{e:#}")))?;
+    if changes.is_empty() {
+        return Err(error_response("Rename changes nothing"));
+    }
+
+    let text = if apply {
+        // Indexing could also have started again, e.g. because a `Cargo.toml` changed
+        ensure_indexed(&project)?;
+        write_changes(&changes).map_err(|e| error_response(&format!("{e:#}")))?;
+        let files = changes
+            .iter()
+            .map(|change| format!("- {}", change.path.display()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "Renamed `{symbol}` to `{new_name}` in {} files:\n{files}",
+            changes.len()
+        )
+    } else {
+        format!(
+            "Changes of renaming `{symbol}` to `{new_name}` (not applied, call again with `apply: true` to write them to disk):\n```diff\n{}```",
+            format_changes(&changes)
+        )
+    };
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text { text }],
+        is_error: None,
+        meta: None,
+    })
+}
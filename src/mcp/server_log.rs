@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use crate::context::{Context, ProjectContext};
+use anyhow::Result;
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{McpNotification, utils::get_info_from_request};
+
+const DEFAULT_LINES: u64 = 100;
+
+pub struct ServerLog;
+
+impl ServerLog {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "server_log".to_string(),
+            description: Some("Get the latest output of the project's rust-analyzer: its stderr and the messages it sent, e.g. that it failed to load the workspace or that the proc-macro server crashed. Useful when other tools return no or unexpected results.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to any file in the project, e.g. its `Cargo.toml`"
+                    },
+                    "lines": {
+                        "type": "number",
+                        "description": "The number of lines to return, the most recent ones. Default is 100."
+                    }
+                },
+                "required": ["file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match handle_request(project, &relative_file, &request).await {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    _relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let lines = request
+        .arguments
+        .as_ref()
+        .and_then(|args| args.get("lines"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_LINES);
+
+    let log = project
+        .server_log
+        .tail(lines as usize)
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>();
+    let text = if log.is_empty() {
+        "rust-analyzer did not log anything yet".to_string()
+    } else {
+        log.join("\n")
+    };
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text { text }],
+        is_error: None,
+        meta: None,
+    })
+}
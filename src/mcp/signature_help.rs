@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use crate::context::{Context, ProjectContext};
+use anyhow::Result;
+use lsp_types::{Documentation, ParameterLabel, Position, SignatureInformation};
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, error_response, get_info_from_request, read_content, when_indexed,
+        with_content,
+    },
+};
+
+pub struct SignatureHelp;
+
+impl SignatureHelp {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "signature_help".to_string(),
+            description: Some("Get the signature help for a call site: every signature of the called function, the documentation of its parameters and which argument the cursor is on. The cursor is either given by `column` or placed into the argument list of the `call` found on the line.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file containing the call"
+                    },
+                    "line": {
+                        "type": "number",
+                        "description": "The line number of the call in the file (1 based)"
+                    },
+                    "column": {
+                        "type": "number",
+                        "description": "The column of the cursor inside the argument list (1 based)"
+                    },
+                    "call": {
+                        "type": "string",
+                        "description": "The call expression on the line, e.g. `foo(` or `client.send(request, ` to place the cursor at the end of it. Used if no column is given."
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Optional unsaved content of the file to use instead of the file on disk"
+                    }
+                },
+                "required": ["file", "line"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let content = request.get_content();
+    with_content(
+        &project,
+        relative_file,
+        content,
+        signature_help(&project, relative_file, request),
+    )
+    .await?
+}
+
+async fn signature_help(
+    project: &Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let arguments = request.arguments.as_ref();
+    let column = arguments
+        .and_then(|args| args.get("column"))
+        .and_then(|v| v.as_u64());
+    let call = arguments
+        .and_then(|args| args.get("call"))
+        .and_then(|v| v.as_str());
+
+    let content = read_content(project, relative_file, request.get_content().as_deref())?;
+    let index = line.saturating_sub(1) as usize;
+    let Some(text) = content.lines().nth(index) else {
+        return Err(error_response(&format!("Line {line} not found in file")));
+    };
+
+    let byte_column = match (column, call) {
+        (Some(column), _) => text
+            .char_indices()
+            .nth(column.saturating_sub(1) as usize)
+            .map(|(offset, _)| offset)
+            .unwrap_or(text.len()),
+        (None, Some(call)) => call_cursor(text, call)
+            .ok_or_else(|| error_response(&format!("Call {call} not found on line {line}")))?,
+        (None, None) => return Err(error_response("Either column or call is required")),
+    };
+    let position = Position::new(
+        index as u32,
+        text[..byte_column].encode_utf16().count() as u32,
+    );
+
+    let Some(help) = project
+        .lsp
+        .signature_help(relative_file, position)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+    else {
+        return Err(error_response("No signature help found at this position"));
+    };
+    if help.signatures.is_empty() {
+        return Err(error_response("No signature help found at this position"));
+    }
+
+    let active_signature = help.active_signature.unwrap_or(0) as usize;
+    let count = help.signatures.len();
+    let contents = help
+        .signatures
+        .iter()
+        .enumerate()
+        .map(|(index, signature)| {
+            let active_parameter = signature.active_parameter.or(help.active_parameter);
+            let active = if index == active_signature {
+                " (active)"
+            } else {
+                ""
+            };
+            format!(
+                "## Signature {} of {count}{active}\n{}",
+                index + 1,
+                format_signature(signature, active_parameter)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: contents.join("\n"),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+// The cursor goes to the end of the call if it already contains the opening parenthesis,
+// otherwise right behind the parenthesis following it.
+fn call_cursor(text: &str, call: &str) -> Option<usize> {
+    let start = text.find(call)?;
+    let end = start + call.len();
+    if call.contains('(') {
+        return Some(end);
+    }
+    text[end..].find('(').map(|offset| end + offset + 1)
+}
+
+fn format_signature(signature: &SignatureInformation, active_parameter: Option<u32>) -> String {
+    let mut lines = vec![format!("`{}`", signature.label)];
+
+    let parameters = signature.parameters.as_deref().unwrap_or_default();
+    if !parameters.is_empty() {
+        lines.push("Parameters:".to_string());
+    }
+    for (index, parameter) in parameters.iter().enumerate() {
+        let label = match &parameter.label {
+            ParameterLabel::Simple(label) => label.clone(),
+            ParameterLabel::LabelOffsets([start, end]) => {
+                let utf16 = signature.label.encode_utf16().collect::<Vec<_>>();
+                // Offsets outside of the label are a server bug, show the whole signature then
+                match utf16.get(*start as usize..*end as usize) {
+                    Some(label) => String::from_utf16_lossy(label),
+                    None => signature.label.clone(),
+                }
+            }
+        };
+        let active = if active_parameter == Some(index as u32) {
+            " <- cursor"
+        } else {
+            ""
+        };
+        lines.push(format!("- `{label}`{active}"));
+        if let Some(documentation) = &parameter.documentation {
+            lines.push(format!("  {}", format_documentation(documentation)));
+        }
+    }
+
+    if let Some(documentation) = &signature.documentation {
+        lines.push(format_documentation(documentation));
+    }
+    lines.join("\n")
+}
+
+fn format_documentation(documentation: &Documentation) -> String {
+    match documentation {
+        Documentation::String(text) => text.clone(),
+        Documentation::MarkupContent(content) => content.value.clone(),
+    }
+}
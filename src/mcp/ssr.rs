@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use crate::{
+    context::{Context, ProjectContext},
+    lsp::{format_changes, resolve_workspace_edit, write_changes},
+};
+use anyhow::Result;
+use lsp_types::{Position, Range};
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{ensure_indexed, error_response, get_info_from_request, when_indexed},
+};
+
+pub struct Ssr;
+
+impl Ssr {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "ssr".to_string(),
+            description: Some("Structural search and replace across the workspace using rust-analyzer. Takes a rule like `foo($a, $b) ==>> bar($b, $a)` or `$a.unwrap() ==>> $a?`, where `$name` placeholders match any expression and paths are resolved semantically. Returns the changes as a unified diff; they are only written to disk if `apply` is true.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "rule": {
+                        "type": "string",
+                        "description": "The rule in the form `search ==>> replacement`"
+                    },
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file whose scope is used to resolve the paths in the rule"
+                    },
+                    "line": {
+                        "type": "number",
+                        "description": "The line in the file whose scope is used to resolve the paths in the rule (1 based). Default is 1."
+                    },
+                    "only_file": {
+                        "type": "boolean",
+                        "description": "If true, only `file` is changed instead of the whole workspace. Default is false."
+                    },
+                    "apply": {
+                        "type": "boolean",
+                        "description": "If true, the changes are written to disk. Default is false."
+                    }
+                },
+                "required": ["rule", "file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let arguments = request.arguments.as_ref();
+    let rule = arguments
+        .and_then(|args| args.get("rule"))
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| error_response("Rule is required"))?;
+    let line = arguments
+        .and_then(|args| args.get("line"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1);
+    let only_file = arguments
+        .and_then(|args| args.get("only_file"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let apply = arguments
+        .and_then(|args| args.get("apply"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    // Changes computed while indexing can be incomplete, even if it ended in the meantime
+    if apply {
+        ensure_indexed(&project)?;
+    }
+
+    let selections = if only_file {
+        let content = std::fs::read_to_string(project.project.root().join(relative_file))
+            .map_err(|e| error_response(&format!("{e:#}")))?;
+        let last_line = content.lines().count().saturating_sub(1);
+        let last_line_length = content
+            .lines()
+            .next_back()
+            .map(|line| line.encode_utf16().count())
+            .unwrap_or(0);
+        vec![Range::new(
+            Position::new(0, 0),
+            Position::new(last_line as u32, last_line_length as u32),
+        )]
+    } else {
+        Vec::new()
+    };
+    let position = Position::new(line.saturating_sub(1) as u32, 0);
+
+    let edit = project
+        .lsp
+        .structural_search_replace(relative_file, position, rule, selections)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?;
+
+    let changes = resolve_workspace_edit(&edit).map_err(|e| error_response(&format!("{e:#}")))?;
+    if changes.is_empty() {
+        return Err(error_response(&format!("No matches found for {rule}")));
+    }
+
+    let text = if apply {
+        // Indexing could also have started again, e.g. because a `Cargo.toml` changed
+        ensure_indexed(&project)?;
+        write_changes(&changes).map_err(|e| error_response(&format!("{e:#}")))?;
+        let files = changes
+            .iter()
+            .map(|change| format!("- {}", change.path.display()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("Applied `{rule}` in {} files:\n{files}", changes.len())
+    } else {
+        format!(
+            "Changes of `{rule}` (not applied, call again with `apply: true` to write them to disk):\n```diff\n{}```",
+            format_changes(&changes)
+        )
+    };
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text { text }],
+        is_error: None,
+        meta: None,
+    })
+}
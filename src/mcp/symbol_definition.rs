@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use crate::{
+    context::{Context, ProjectContext},
+    lsp::get_locations,
+};
+use anyhow::Result;
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, error_response, find_symbol_position_on_line, get_file_lines,
+        get_info_from_request, when_indexed,
+    },
+};
+
+pub struct SymbolDefinition;
+
+impl SymbolDefinition {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "symbol_definition".to_string(),
+            description: Some("Go to the declaration of a symbol. The symbol can be a usage anywhere on the given line (e.g. a function call or a local variable). Returns the file and line of every declaration including a preview, also for declarations inside of dependencies.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "line": {
+                        "type": "number",
+                        "description": "The line number of the symbol in the file (1 based)"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "The name of the symbol to find the declaration for"
+                    },
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file containing the symbol"
+                    }
+                },
+                "required": ["line", "symbol", "file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let symbol = request.get_symbol()?;
+
+    let position =
+        find_symbol_position_on_line(project.project.root().join(relative_file), &symbol, line)
+            .map_err(|e| error_response(&e))?;
+
+    let Some(definition) = project
+        .lsp
+        .definition(relative_file, position)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+    else {
+        return Err(error_response("No definition found"));
+    };
+
+    let mut contents = String::new();
+    for location in get_locations(definition) {
+        let Ok(path) = location.uri.to_file_path() else {
+            continue;
+        };
+        let Ok(Some(lines)) = get_file_lines(
+            &path,
+            location.range.start.line,
+            location.range.end.line,
+            2,
+            8,
+        ) else {
+            continue;
+        };
+        let origin = if path.starts_with(project.project.root()) {
+            ""
+        } else {
+            " (dependency)"
+        };
+        contents.push_str(&format!(
+            "## {}:{}{}\n```rust\n{}\n```\n",
+            path.display(),
+            location.range.start.line + 1,
+            origin,
+            lines
+        ));
+    }
+
+    if contents.is_empty() {
+        return Err(error_response("No definition found"));
+    }
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text { text: contents }],
+        is_error: None,
+        meta: None,
+    })
+}
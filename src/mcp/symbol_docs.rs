@@ -16,6 +16,7 @@ use super::{
     McpNotification,
     utils::{
         RequestExtension, error_response, find_symbol_position_in_file, get_info_from_request,
+        when_indexed, with_content,
     },
 };
 
@@ -40,6 +41,10 @@ impl SymbolDocs {
                     "file": {
                         "type": "string",
                         "description": "The absolute path to the file containing the symbol"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Optional unsaved content of the file to use instead of the file on disk"
                     }
                 },
                 "required": ["line", "symbol", "file"]
@@ -65,7 +70,12 @@ impl SymbolDocs {
                 {
                     tracing::error!("Failed to send MCP notification: {}", e);
                 }
-                let response = match handle_request(project, &relative_file, &request).await {
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
                     Ok(response) => response,
                     Err(response) => response,
                 };
@@ -88,11 +98,26 @@ async fn handle_request(
     project: Arc<ProjectContext>,
     relative_file: &str,
     request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let content = request.get_content();
+    with_content(
+        &project,
+        relative_file,
+        content,
+        hover_symbol(&project, relative_file, request),
+    )
+    .await?
+}
+
+async fn hover_symbol(
+    project: &Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
 ) -> Result<CallToolResponse, CallToolResponse> {
     let line = request.get_line()?;
     let symbol = request.get_symbol()?;
 
-    let position = find_symbol_position_in_file(&project, relative_file, &symbol, line)
+    let position = find_symbol_position_in_file(project, relative_file, &symbol, line)
         .await
         .map_err(|e| error_response(&e))?;
 
@@ -100,7 +125,7 @@ async fn handle_request(
         .lsp
         .hover(relative_file, position)
         .await
-        .map_err(|e| error_response(&e.to_string()))?
+        .map_err(|e| error_response(&format!("{e:#}")))?
     else {
         return Err(error_response("No hover information found"));
     };
@@ -1,8 +1,8 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use crate::{
     context::{Context, ProjectContext},
-    lsp::get_location_contents,
+    lsp::{get_location_contents, get_locations},
 };
 use anyhow::Result;
 use mcp_core::{
@@ -14,7 +14,8 @@ use serde_json::json;
 use super::{
     McpNotification,
     utils::{
-        RequestExtension, error_response, find_symbol_position_in_file, get_info_from_request,
+        RequestExtension, error_response, find_enclosing_items, find_symbol_position_in_file,
+        get_info_from_request, get_numbered_file_lines, when_indexed,
     },
 };
 
@@ -24,7 +25,7 @@ impl SymbolImpl {
     pub fn tool() -> Tool {
         Tool {
             name: "symbol_impl".to_string(),
-            description: Some("Get the implementation for a symbol. If the implementation is in multiple files, will return multiple files. Will return the full file that contains the implementation including other contents of the file.".to_string()),
+            description: Some("Get the implementation for a symbol. Returns only the item that implements the symbol (e.g. the function, the impl block or the struct including its impls) with line numbers. If the implementation is in multiple files, will return multiple items. Set `full_file` to return the full files instead.".to_string()),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -39,6 +40,10 @@ impl SymbolImpl {
                     "file": {
                         "type": "string",
                         "description": "The absolute path to the file containing the symbol"
+                    },
+                    "full_file": {
+                        "type": "boolean",
+                        "description": "If true, the full file containing the implementation is returned. Default is false."
                     }
                 },
                 "required": ["line", "symbol", "file"]
@@ -64,7 +69,12 @@ impl SymbolImpl {
                 {
                     tracing::error!("Failed to send MCP notification: {}", e);
                 }
-                let response = match handle_request(project, &relative_file, &request).await {
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
                     Ok(response) => response,
                     Err(response) => response,
                 };
@@ -99,26 +109,61 @@ async fn handle_request(
         .lsp
         .type_definition(relative_file, position)
         .await
-        .map_err(|e| error_response(&e.to_string()))?
+        .map_err(|e| error_response(&format!("{e:#}")))?
     else {
         return Err(error_response("No type definition found"));
     };
 
-    let contents = get_location_contents(type_definition)
-        .map_err(|e| error_response(&e.to_string()))?
-        .iter()
-        .map(|(content, path)| {
-            format!(
-                r#"## {}
+    let full_file = request
+        .arguments
+        .as_ref()
+        .and_then(|args| args.get("full_file"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let contents = if full_file {
+        get_location_contents(type_definition)
+            .map_err(|e| error_response(&format!("{e:#}")))?
+            .iter()
+            .map(|(content, path)| {
+                format!(
+                    r#"## {}
 ``` rust
 {}
 ```"#,
-                path.display(),
-                content
-            )
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
+                    path.display(),
+                    content
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        let mut known_items = HashSet::new();
+        let mut items = Vec::new();
+        for location in get_locations(type_definition) {
+            let Ok(path) = location.uri.to_file_path() else {
+                continue;
+            };
+            for range in find_enclosing_items(&project, &path, location.range).await {
+                if !known_items.insert((path.clone(), range.start.line, range.end.line)) {
+                    continue;
+                }
+                let Ok(Some(lines)) =
+                    get_numbered_file_lines(&path, range.start.line, range.end.line, 3)
+                else {
+                    continue;
+                };
+                items.push(format!(
+                    "## {}:{}-{}\n```rust\n{}\n```",
+                    path.display(),
+                    range.start.line + 1,
+                    range.end.line + 1,
+                    lines
+                ));
+            }
+        }
+        items.join("\n")
+    };
 
     Ok(CallToolResponse {
         content: vec![ToolResponseContent::Text { text: contents }],
@@ -12,7 +12,7 @@ use super::{
     McpNotification,
     utils::{
         RequestExtension, error_response, find_symbol_position_in_file, get_file_lines,
-        get_info_from_request,
+        get_info_from_request, when_indexed,
     },
 };
 
@@ -64,7 +64,12 @@ impl SymbolReferences {
                     tracing::error!("Failed to send MCP notification: {}", e);
                 }
                 tracing::debug!("Sending MCP notification for symbol references");
-                let response = match handle_request(project, &relative_file, &request).await {
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
                     Ok(response) => response,
                     Err(response) => response,
                 };
@@ -99,7 +104,7 @@ async fn handle_request(
         .lsp
         .find_references(relative_file, position)
         .await
-        .map_err(|e| error_response(&e.to_string()))?
+        .map_err(|e| error_response(&format!("{e:#}")))?
     else {
         return Err(error_response("No references found"));
     };
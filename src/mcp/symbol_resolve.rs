@@ -2,7 +2,7 @@ use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     context::{Context, ProjectContext},
-    lsp::format_marked_string,
+    lsp::{flatten_document_symbols, format_marked_string},
 };
 use anyhow::Result;
 use fuzzt::get_top_n;
@@ -15,7 +15,7 @@ use serde_json::json;
 
 use super::{
     McpNotification,
-    utils::{RequestExtension, error_response, get_info_from_request},
+    utils::{RequestExtension, error_response, get_info_from_request, when_indexed},
 };
 
 pub struct SymbolResolve;
@@ -24,13 +24,13 @@ impl SymbolResolve {
     pub fn tool() -> Tool {
         Tool {
             name: "symbol_docs".to_string(),
-            description: Some("Resolve a symbol based on its name. Provide any symbol from the file and it will try to resolve it and return documentation about it.".to_string()),
+            description: Some("Resolve a symbol based on its name. Provide any symbol from the file and it will try to resolve it and return documentation about it. Accepts qualified names such as `Type::method` to pick between same-named methods.".to_string()),
             input_schema: json!({
                 "type": "object",
                 "properties": {
                     "symbol": {
                         "type": "string",
-                        "description": "The name of the symbol to get the documentation for"
+                        "description": "The name of the symbol to get the documentation for, optionally qualified (e.g. `Docs::update_index`)"
                     },
                     "file": {
                         "type": "string",
@@ -60,7 +60,12 @@ impl SymbolResolve {
                 {
                     tracing::error!("Failed to send MCP notification: {}", e);
                 }
-                let response = match handle_request(project, &relative_file, &request).await {
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
                     Ok(response) => response,
                     Err(response) => response,
                 };
@@ -89,13 +94,13 @@ async fn handle_request(
     let symbols = match project.lsp.document_symbols(relative_file).await {
         Ok(Some(symbols)) => symbols,
         Ok(None) => return Err(error_response("No symbols found")),
-        Err(e) => return Err(error_response(&e.to_string())),
+        Err(e) => return Err(error_response(&format!("{e:#}"))),
     };
 
     let mut symbol_map = HashMap::new();
 
-    for file_symbol in symbols {
-        symbol_map.insert(file_symbol.name.clone(), file_symbol);
+    for (qualified_name, file_symbol) in flatten_document_symbols(&symbols) {
+        symbol_map.insert(qualified_name, file_symbol);
     }
 
     let keys = symbol_map.keys().map(|s| s.as_str()).collect::<Vec<_>>();
@@ -110,18 +115,18 @@ async fn handle_request(
         return Err(error_response("No match for symbol found"));
     };
 
-    let position = symbol_match.location.range.start;
+    let position = symbol_match.selection_range.start;
 
     let Some(hover) = project
         .lsp
         .hover(relative_file, position)
         .await
-        .map_err(|e| error_response(&e.to_string()))?
+        .map_err(|e| error_response(&format!("{e:#}")))?
     else {
         return Err(error_response("No hover information found"));
     };
 
-    let response = match hover.contents {
+    let documentation = match hover.contents {
         HoverContents::Scalar(s) => format_marked_string(&s),
         HoverContents::Array(a) => a
             .into_iter()
@@ -130,6 +135,7 @@ async fn handle_request(
             .join("\n"),
         HoverContents::Markup(m) => m.value,
     };
+    let response = format!("`{match_str}`\n{documentation}");
 
     Ok(CallToolResponse {
         content: vec![ToolResponseContent::Text { text: response }],
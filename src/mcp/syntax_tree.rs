@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use crate::context::{Context, ProjectContext};
+use anyhow::Result;
+use lsp_types::{Position, Range};
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{error_response, get_info_from_request, when_indexed},
+};
+
+pub struct SyntaxTree;
+
+impl SyntaxTree {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "syntax_tree".to_string(),
+            description: Some("Get the syntax tree rust-analyzer parsed for a file or a line range, with the kind and range (`line:column`, 1 based) of every node and token. Useful to check how a piece of code, e.g. inside a macro heavy crate, is actually parsed.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file"
+                    },
+                    "line": {
+                        "type": "number",
+                        "description": "The first line of the range (1 based). Default is the whole file."
+                    },
+                    "end_line": {
+                        "type": "number",
+                        "description": "The last line of the range (1 based). Default is `line`."
+                    }
+                },
+                "required": ["file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let arguments = request.arguments.as_ref();
+    let line = arguments
+        .and_then(|args| args.get("line"))
+        .and_then(|v| v.as_u64());
+
+    let range = match line {
+        Some(line) => {
+            let end_line = arguments
+                .and_then(|args| args.get("end_line"))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(line)
+                .max(line);
+            let content = std::fs::read_to_string(project.project.root().join(relative_file))
+                .map_err(|e| error_response(&format!("{e:#}")))?;
+            let end_index = end_line.saturating_sub(1) as usize;
+            let Some(last_line) = content.lines().nth(end_index) else {
+                return Err(error_response(&format!(
+                    "Line {end_line} not found in file"
+                )));
+            };
+            Some(Range::new(
+                Position::new(line.saturating_sub(1) as u32, 0),
+                Position::new(end_index as u32, last_line.encode_utf16().count() as u32),
+            ))
+        }
+        None => None,
+    };
+
+    let tree = project
+        .lsp
+        .syntax_tree(relative_file, range)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?;
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: format!("```\n{}\n```", tree.trim_end()),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use crate::{
+    context::{Context, ProjectContext},
+    lsp::MemoryLayoutNode,
+};
+use anyhow::Result;
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, error_response, find_symbol_position_on_line, get_info_from_request,
+        when_indexed,
+    },
+};
+
+pub struct TypeLayout;
+
+impl TypeLayout {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "type_layout".to_string(),
+            description: Some("Get the memory layout of a type: its size and alignment, and recursively the offset, size and alignment of every field including the padding between them. Works on a type definition or on a binding, field or expression of that type.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "line": {
+                        "type": "number",
+                        "description": "The line number of the type or binding in the file (1 based)"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "The name of the type or binding to get the layout for"
+                    },
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file containing the type"
+                    }
+                },
+                "required": ["line", "symbol", "file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let symbol = request.get_symbol()?;
+
+    let position =
+        find_symbol_position_on_line(project.project.root().join(relative_file), &symbol, line)
+            .map_err(|e| error_response(&e))?;
+
+    let Some(layout) = project
+        .lsp
+        .memory_layout(relative_file, position)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+    else {
+        return Err(error_response(&format!("No layout found for {symbol}")));
+    };
+    let Some(root) = layout.nodes.first() else {
+        return Err(error_response(&format!("No layout found for {symbol}")));
+    };
+
+    let mut lines = vec![format!(
+        "`{}`: `{}` size {}, align {}",
+        root.item_name, root.typename, root.size, root.alignment
+    )];
+    write_children(&layout.nodes, 0, 1, &mut lines);
+    // rust-analyzer only shows them in the hover text, which is not meant to be parsed
+    lines.push(
+        "Niches: unknown, the layout rust-analyzer reports does not include them".to_string(),
+    );
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: lines.join("\n"),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+fn write_children(nodes: &[MemoryLayoutNode], index: usize, depth: usize, lines: &mut Vec<String>) {
+    let node = &nodes[index];
+    if node.children_start < 0 {
+        return;
+    }
+    let start = node.children_start as usize;
+    let end = (start + node.children_len as usize).min(nodes.len());
+    let mut children = (start..end).collect::<Vec<_>>();
+    children.sort_by_key(|child| nodes[*child].offset);
+
+    let indent = "  ".repeat(depth);
+    let mut cursor = 0;
+    for child_index in children {
+        let child = &nodes[child_index];
+        if child.offset > cursor {
+            lines.push(format!(
+                "{indent}- {cursor}..{}: padding ({} bytes)",
+                child.offset,
+                child.offset - cursor
+            ));
+        }
+        lines.push(format!(
+            "{indent}- {}: {} `{}` size {}, align {}",
+            child.offset, child.item_name, child.typename, child.size, child.alignment
+        ));
+        write_children(nodes, child_index, depth + 1, lines);
+        cursor = cursor.max(child.offset + child.size);
+    }
+    if cursor < node.size {
+        lines.push(format!(
+            "{indent}- {cursor}..{}: padding ({} bytes)",
+            node.size,
+            node.size - cursor
+        ));
+    }
+}
@@ -1,11 +1,16 @@
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::context::{Context, ProjectContext};
+use crate::lsp::{flatten_document_symbols, impl_self_type, range_contains};
 use anyhow::Result;
-use lsp_types::Position;
+use lsp_types::{Position, Range, SymbolKind};
 use mcp_core::types::{CallToolRequest, CallToolResponse, ToolResponseContent};
 
+// Tools wait this long for rust-analyzer to finish indexing before they answer anyway
+const INDEXING_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub fn error_response(message: &str) -> CallToolResponse {
     CallToolResponse {
         content: vec![ToolResponseContent::Text {
@@ -20,6 +25,7 @@ pub(super) trait RequestExtension {
     fn get_line(&self) -> Result<u64, CallToolResponse>;
     fn get_symbol(&self) -> Result<String, CallToolResponse>;
     fn get_file(&self) -> Result<String, CallToolResponse>;
+    fn get_content(&self) -> Option<String>;
 }
 
 impl RequestExtension for CallToolRequest {
@@ -58,6 +64,83 @@ impl RequestExtension for CallToolRequest {
             .ok_or_else(|| error_response("File is required"))
             .map(|s| s.to_string())
     }
+
+    fn get_content(&self) -> Option<String> {
+        self.arguments
+            .as_ref()
+            .and_then(|args| args.get("content"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    }
+}
+
+/// Runs `future` while rust-analyzer sees `content` instead of the file on disk. Without
+/// `content` it waits until no other call has unsaved content of the file open.
+pub async fn with_content<T>(
+    project: &ProjectContext,
+    relative_file: &str,
+    content: Option<String>,
+    future: impl Future<Output = T>,
+) -> Result<T, CallToolResponse> {
+    let Some(content) = content else {
+        let _lock = project
+            .lsp
+            .lock_document(relative_file)
+            .await
+            .map_err(|e| error_response(&format!("{e:#}")))?;
+        return Ok(future.await);
+    };
+    // Closed when it is dropped, also if the tool call is cancelled
+    let _overlay = project
+        .lsp
+        .open_overlay(relative_file, content)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?;
+    Ok(future.await)
+}
+
+/// Runs `future` once rust-analyzer finished indexing. If that takes too long, `future` runs
+/// anyway and the response starts with a note that its results might be incomplete.
+pub async fn when_indexed(
+    project: &ProjectContext,
+    future: impl Future<Output = Result<CallToolResponse, CallToolResponse>>,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let status = project.lsp.wait_for_indexing(INDEXING_TIMEOUT).await;
+    let mut result = future.await;
+    if status.is_indexing {
+        let (Ok(response) | Err(response)) = &mut result;
+        response.content.insert(
+            0,
+            ToolResponseContent::Text {
+                text: format!("rust-analyzer is still {status}, results may be incomplete"),
+            },
+        );
+    }
+    result
+}
+
+/// Fails while rust-analyzer is still indexing, changes computed by it could miss references
+/// it has not seen yet and must not be written to disk.
+pub fn ensure_indexed(project: &ProjectContext) -> Result<(), CallToolResponse> {
+    let status = project.lsp.indexing_status();
+    if status.is_indexing {
+        return Err(error_response(&format!(
+            "rust-analyzer is still {status}, the changes may be incomplete and were not applied. Call again once it finished, or without `apply` to preview them"
+        )));
+    }
+    Ok(())
+}
+
+pub fn read_content(
+    project: &ProjectContext,
+    relative_file: &str,
+    content: Option<&str>,
+) -> Result<String, CallToolResponse> {
+    match content {
+        Some(content) => Ok(content.to_string()),
+        None => std::fs::read_to_string(project.project.root().join(relative_file))
+            .map_err(|e| error_response(&format!("{e:#}"))),
+    }
 }
 
 pub async fn get_info_from_request(
@@ -89,12 +172,152 @@ pub async fn find_symbol_position_in_file(
         Ok(None) => return Err("No symbols found".to_string()),
         Err(e) => return Err(e.to_string()),
     };
-    for symbol in symbols {
-        if symbol.location.range.start.line == line as u32 {
-            return Ok(symbol.location.range.start);
+    let index = line.saturating_sub(1) as u32;
+    let candidates = flatten_document_symbols(&symbols)
+        .into_iter()
+        .filter(|(qualified, s)| qualified == symbol || s.name == symbol)
+        .map(|(_, s)| s)
+        .collect::<Vec<_>>();
+    if let Some(candidate) = candidates
+        .iter()
+        .filter(|s| s.range.start.line <= index && index <= s.range.end.line)
+        .min_by_key(|s| s.range.end.line - s.range.start.line)
+    {
+        return Ok(candidate.selection_range.start);
+    }
+    if candidates.is_empty() {
+        return Err(format!("Symbol {symbol} not found in file {relative_file}"));
+    }
+    // Guessing one of them could silently answer for a different symbol
+    let lines = candidates
+        .iter()
+        .map(|s| (s.selection_range.start.line + 1).to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(format!(
+        "Symbol {symbol} not found on line {line} of {relative_file}, it is declared on line {lines}"
+    ))
+}
+
+pub fn find_symbol_position_on_line(
+    file_path: impl AsRef<Path>,
+    symbol: &str,
+    line: u64,
+) -> Result<Position, String> {
+    let content = std::fs::read_to_string(file_path).map_err(|e| e.to_string())?;
+    let index = line.saturating_sub(1) as usize;
+    let Some(text) = content.lines().nth(index) else {
+        return Err(format!("Line {line} not found in file"));
+    };
+    let name = symbol.rsplit("::").next().unwrap_or(symbol);
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let Some((column, _)) = text.match_indices(name).find(|(start, _)| {
+        let before = text[..*start].chars().next_back();
+        let after = text[start + name.len()..].chars().next();
+        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    }) else {
+        return Err(format!("Symbol {symbol} not found on line {line}"));
+    };
+    Ok(Position {
+        line: index as u32,
+        character: text[..column].encode_utf16().count() as u32,
+    })
+}
+
+const NON_ITEM_KINDS: &[SymbolKind] = &[
+    SymbolKind::FIELD,
+    SymbolKind::ENUM_MEMBER,
+    SymbolKind::VARIABLE,
+    SymbolKind::TYPE_PARAMETER,
+];
+
+/// Returns the ranges of the innermost item containing `range`. For structs and enums
+/// the impl blocks of the type in the same file are returned as well.
+pub async fn find_enclosing_items(
+    project: &Arc<ProjectContext>,
+    file_path: &Path,
+    range: Range,
+) -> Vec<Range> {
+    let symbols = match project.lsp.document_symbols(file_path).await {
+        Ok(Some(symbols)) => symbols,
+        Ok(None) => return vec![range],
+        Err(e) => {
+            tracing::error!("Failed to get symbols for {:?}: {}", file_path, e);
+            return vec![range];
         }
+    };
+    let symbols = flatten_document_symbols(&symbols)
+        .into_iter()
+        .map(|(_, s)| s)
+        .collect::<Vec<_>>();
+    let Some(item) = symbols
+        .iter()
+        .filter(|s| !NON_ITEM_KINDS.contains(&s.kind) && range_contains(&s.range, range.start))
+        .min_by_key(|s| s.range.end.line - s.range.start.line)
+    else {
+        return vec![range];
+    };
+
+    let mut ranges = vec![item.range];
+    if [SymbolKind::STRUCT, SymbolKind::ENUM].contains(&item.kind) {
+        ranges.extend(
+            symbols
+                .iter()
+                .filter(|s| {
+                    s.kind == SymbolKind::OBJECT
+                        && impl_self_type(&s.name) == Some(item.name.as_str())
+                })
+                .map(|s| s.range),
+        );
     }
-    Err(format!("Symbol {symbol} not found in file {relative_file}"))
+    ranges
+}
+
+/// The snake case name of an LSP kind like `SymbolKind` or `CompletionItemKind`, e.g.
+/// `enum_member`.
+pub fn kind_name(kind: impl std::fmt::Debug) -> String {
+    let pascal_case = format!("{kind:?}");
+    let mut snake_case = String::new();
+    for (index, c) in pascal_case.char_indices() {
+        if c.is_uppercase() && index > 0 {
+            snake_case.push('_');
+        }
+        snake_case.extend(c.to_lowercase());
+    }
+    snake_case
+}
+
+pub fn get_numbered_file_lines(
+    file_path: impl AsRef<Path>,
+    start_line: u32,
+    end_line: u32,
+    context: u32,
+) -> std::io::Result<Option<String>> {
+    let content = std::fs::read_to_string(file_path)?;
+    Ok(format_numbered_lines(
+        &content, start_line, end_line, context,
+    ))
+}
+
+pub fn format_numbered_lines(
+    content: &str,
+    start_line: u32,
+    end_line: u32,
+    context: u32,
+) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.is_empty() || start_line as usize >= lines.len() {
+        return None;
+    }
+
+    let start = start_line.saturating_sub(context) as usize;
+    let end = (end_line.saturating_add(context) as usize).min(lines.len() - 1);
+
+    let numbered = (start..=end)
+        .map(|index| format!("{:>5} | {}", index + 1, lines[index]))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(numbered)
 }
 
 pub fn get_file_lines(
@@ -111,12 +334,12 @@ pub fn get_file_lines(
     let start = start_line.saturating_sub(prefix as u32);
     let mut end = end_line.saturating_add(suffix as u32);
 
-    if end > lines.len() as u32 {
-        end = lines.len() as u32;
+    if end >= lines.len() as u32 {
+        end = (lines.len() as u32).saturating_sub(1);
     }
 
     
-    if start > end || end >= lines.len() as u32 {
+    if lines.is_empty() || start > end {
         return Ok(None);
     }
 
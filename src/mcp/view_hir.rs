@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use crate::context::{Context, ProjectContext};
+use anyhow::Result;
+use lsp_types::Position;
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{
+        RequestExtension, error_response, find_symbol_position_on_line, get_info_from_request,
+        when_indexed,
+    },
+};
+
+pub struct ViewHir;
+
+impl ViewHir {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "view_hir".to_string(),
+            description: Some("Get the HIR (the desugared, name resolved intermediate representation rust-analyzer infers types on) of the function containing a line. Useful to debug why a type is inferred differently than expected.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the file"
+                    },
+                    "line": {
+                        "type": "number",
+                        "description": "A line inside of the function (1 based)"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "Optional symbol on the line to place the cursor on. Default is the first non whitespace character of the line."
+                    }
+                },
+                "required": ["file", "line"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let line = request.get_line()?;
+    let absolute_file = project.project.root().join(relative_file);
+
+    let position = match request.get_symbol() {
+        Ok(symbol) => find_symbol_position_on_line(&absolute_file, &symbol, line)
+            .map_err(|e| error_response(&e))?,
+        Err(_) => {
+            let content = std::fs::read_to_string(&absolute_file)
+                .map_err(|e| error_response(&format!("{e:#}")))?;
+            let index = line.saturating_sub(1) as usize;
+            let Some(text) = content.lines().nth(index) else {
+                return Err(error_response(&format!("Line {line} not found in file")));
+            };
+            let indent = text.len() - text.trim_start().len();
+            Position::new(index as u32, text[..indent].encode_utf16().count() as u32)
+        }
+    };
+
+    let hir = project
+        .lsp
+        .view_hir(relative_file, position)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?;
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: format!("```\n{}\n```", hir.trim_end()),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
@@ -0,0 +1,247 @@
+use std::sync::Arc;
+
+use crate::context::{Context, ProjectContext};
+use anyhow::Result;
+use lsp_types::{OneOf, SymbolKind, Url, WorkspaceSymbolResponse};
+use mcp_core::{
+    tools::ToolHandlerFn,
+    types::{CallToolRequest, CallToolResponse, Tool, ToolResponseContent},
+};
+use serde_json::json;
+
+use super::{
+    McpNotification,
+    utils::{error_response, get_info_from_request, kind_name, when_indexed},
+};
+
+pub struct WorkspaceSymbols;
+
+impl WorkspaceSymbols {
+    pub fn tool() -> Tool {
+        Tool {
+            name: "workspace_symbols".to_string(),
+            description: Some("Search for symbols in the whole project by a fuzzy name without knowing the file they are declared in. Returns the kind, name, container, file path and line (1 based) of every match.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "The fuzzy name of the symbol to search for"
+                    },
+                    "kind": {
+                        "type": "string",
+                        "description": "Optional kind to filter the symbols by, e.g. `struct`, `enum`, `trait`, `function`, `method`, `module`, `constant`, `type_parameter` or `enum_member`"
+                    },
+                    "include_dependencies": {
+                        "type": "boolean",
+                        "description": "If true, symbols of the dependencies are searched as well. Default is false."
+                    },
+                    "limit": {
+                        "type": "number",
+                        "description": "The maximum number of symbols to return. Default is 50."
+                    },
+                    "file": {
+                        "type": "string",
+                        "description": "The absolute path to the project root or any file in the project, e.g. its `Cargo.toml`"
+                    }
+                },
+                "required": ["query", "file"]
+            }),
+        }
+    }
+
+    pub fn call(context: Context) -> ToolHandlerFn {
+        Box::new(move |request: CallToolRequest| {
+            let clone = context.clone();
+            Box::pin(async move {
+                let (project, relative_file, absolute_file) =
+                    match get_info_from_request(&clone, &request).await {
+                        Ok(info) => info,
+                        Err(response) => return response,
+                    };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Request {
+                        content: request.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                let response = match when_indexed(
+                    &project,
+                    handle_request(project.clone(), &relative_file, &request),
+                )
+                .await
+                {
+                    Ok(response) => response,
+                    Err(response) => response,
+                };
+                if let Err(e) = clone
+                    .send_mcp_notification(McpNotification::Response {
+                        content: response.clone(),
+                        project: absolute_file.clone(),
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to send MCP notification: {}", e);
+                }
+                response
+            })
+        })
+    }
+}
+
+struct SymbolMatch {
+    name: String,
+    kind: SymbolKind,
+    container_name: Option<String>,
+    uri: Url,
+    line: Option<u32>,
+}
+
+async fn handle_request(
+    project: Arc<ProjectContext>,
+    _relative_file: &str,
+    request: &CallToolRequest,
+) -> Result<CallToolResponse, CallToolResponse> {
+    let arguments = request.arguments.as_ref();
+    let query = arguments
+        .and_then(|args| args.get("query"))
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| error_response("Query is required"))?;
+    let kind = match arguments
+        .and_then(|args| args.get("kind"))
+        .and_then(|v| v.as_str())
+    {
+        Some(kind) => Some(
+            parse_symbol_kind(kind)
+                .ok_or_else(|| error_response(&format!("Unknown symbol kind {kind}")))?,
+        ),
+        None => None,
+    };
+    let include_dependencies = arguments
+        .and_then(|args| args.get("include_dependencies"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let limit = arguments
+        .and_then(|args| args.get("limit"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(50) as usize;
+
+    let Some(response) = project
+        .lsp
+        .workspace_symbols(query, include_dependencies)
+        .await
+        .map_err(|e| error_response(&format!("{e:#}")))?
+    else {
+        return Err(error_response("No symbols found"));
+    };
+
+    let symbols = match response {
+        WorkspaceSymbolResponse::Flat(symbols) => symbols
+            .into_iter()
+            .map(|symbol| SymbolMatch {
+                name: symbol.name,
+                kind: symbol.kind,
+                container_name: symbol.container_name,
+                uri: symbol.location.uri,
+                line: Some(symbol.location.range.start.line),
+            })
+            .collect::<Vec<_>>(),
+        WorkspaceSymbolResponse::Nested(symbols) => symbols
+            .into_iter()
+            .map(|symbol| {
+                let (uri, line) = match symbol.location {
+                    OneOf::Left(location) => (location.uri, Some(location.range.start.line)),
+                    OneOf::Right(location) => (location.uri, None),
+                };
+                SymbolMatch {
+                    name: symbol.name,
+                    kind: symbol.kind,
+                    container_name: symbol.container_name,
+                    uri,
+                    line,
+                }
+            })
+            .collect::<Vec<_>>(),
+    };
+
+    let contents = symbols
+        .into_iter()
+        .filter(|symbol| kind.is_none_or(|kind| symbol.kind == kind))
+        .take(limit)
+        .map(|symbol| {
+            let path = symbol
+                .uri
+                .to_file_path()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|_| symbol.uri.to_string());
+            let container = symbol
+                .container_name
+                .map(|container| format!(" in `{container}`"))
+                .unwrap_or_default();
+            let line = symbol
+                .line
+                .map(|line| format!(":{}", line + 1))
+                .unwrap_or_default();
+            format!(
+                "- {} `{}`{} {}{}",
+                kind_name(symbol.kind),
+                symbol.name,
+                container,
+                path,
+                line
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if contents.is_empty() {
+        return Err(error_response("No symbols found"));
+    }
+
+    Ok(CallToolResponse {
+        content: vec![ToolResponseContent::Text {
+            text: contents.join("\n"),
+        }],
+        is_error: None,
+        meta: None,
+    })
+}
+
+fn parse_symbol_kind(kind: &str) -> Option<SymbolKind> {
+    let pascal_case = kind
+        .split('_')
+        .map(|segment| {
+            let mut chars = segment.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect::<String>();
+    SymbolKind::try_from(pascal_case.as_str()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::CompletionItemKind;
+
+    use super::*;
+
+    #[test]
+    fn test_symbol_kind_names() {
+        for kind in [
+            SymbolKind::FUNCTION,
+            SymbolKind::ENUM_MEMBER,
+            SymbolKind::TYPE_PARAMETER,
+        ] {
+            assert_eq!(parse_symbol_kind(&kind_name(kind)), Some(kind));
+        }
+        assert_eq!(kind_name(SymbolKind::ENUM_MEMBER), "enum_member");
+        assert_eq!(
+            kind_name(CompletionItemKind::TYPE_PARAMETER),
+            "type_parameter"
+        );
+    }
+}
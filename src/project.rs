@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use url::Url;
 
+use crate::lsp::RustAnalyzerConfig;
+
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransportType {
     Stdio,
@@ -13,6 +15,8 @@ pub enum TransportType {
 pub struct Project {
     pub root: PathBuf,
     pub ignore_crates: Vec<String>,
+    #[serde(default)]
+    pub rust_analyzer: RustAnalyzerConfig,
 }
 
 impl Project {
@@ -21,6 +25,7 @@ impl Project {
         Ok(Self {
             root,
             ignore_crates: vec![],
+            rust_analyzer: RustAnalyzerConfig::default(),
         })
     }
 
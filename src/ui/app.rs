@@ -1,14 +1,18 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use chrono::{DateTime, Utc};
-use egui::{CentralPanel, Color32, Context as EguiContext, RichText, ScrollArea, SidePanel, Ui};
+use egui::{
+    CentralPanel, Color32, Context as EguiContext, RichText, ScrollArea, SidePanel, TextStyle, Ui,
+};
 use flume::Receiver;
 
 use crate::{
     context::{Context, ContextNotification},
+    lsp::{LspNotification, RustAnalyzerConfig, ServerLog},
     project::Project,
 };
 
@@ -17,7 +21,10 @@ pub struct ProjectDescription {
     pub root: PathBuf,
     pub name: String,
     pub is_indexing_lsp: bool,
+    /// The progress of the indexing step rust-analyzer is working on
+    pub lsp_indexing_percentage: Option<u32>,
     pub is_indexing_docs: bool,
+    pub server_log: ServerLog,
 }
 
 #[derive(Clone, Debug)]
@@ -38,6 +45,12 @@ enum SidebarTab {
     Settings,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+enum ProjectView {
+    Events,
+    ServerLog,
+}
+
 #[derive(Clone, Debug)]
 pub struct TimestampedEvent(DateTime<Utc>, ContextNotification);
 
@@ -54,6 +67,7 @@ pub struct App {
     logs: Vec<String>,
     events: HashMap<String, Vec<TimestampedEvent>>,
     selected_sidebar_tab: SidebarTab,
+    selected_project_view: ProjectView,
     selected_event: Option<TimestampedEvent>,
     projects: Vec<ProjectDescription>,
     settings: Settings,
@@ -73,6 +87,7 @@ impl App {
             logs: Vec::new(),
             events: HashMap::new(),
             selected_sidebar_tab: SidebarTab::Projects,
+            selected_project_view: ProjectView::Events,
             selected_event: None,
             projects,
             settings,
@@ -90,7 +105,10 @@ impl App {
 
             self.context.request_projects();
 
-            if matches!(notification, ContextNotification::Lsp(_)) {
+            if matches!(
+                notification,
+                ContextNotification::Lsp(LspNotification::Indexing { .. })
+            ) {
                 has_new_events = true;
                 continue;
             }
@@ -190,6 +208,7 @@ impl App {
                             .add_project(Project {
                                 root: path_buf,
                                 ignore_crates: vec![],
+                                rust_analyzer: RustAnalyzerConfig::default(),
                             })
                             .await
                         {
@@ -356,9 +375,25 @@ impl App {
                             }
                         }
                         ui.add_space(10.0);
+                        ui.selectable_value(
+                            &mut self.selected_project_view,
+                            ProjectView::Events,
+                            "Events",
+                        );
+                        ui.selectable_value(
+                            &mut self.selected_project_view,
+                            ProjectView::ServerLog,
+                            "Server log",
+                        );
+                        ui.add_space(10.0);
                         if project.is_indexing_lsp {
                             ui.add(egui::Spinner::new());
-                            ui.label("Indexing LSP...");
+                            match project.lsp_indexing_percentage {
+                                Some(percentage) => {
+                                    ui.label(format!("Indexing LSP... {percentage}%"))
+                                }
+                                None => ui.label("Indexing LSP..."),
+                            };
                         }
                         ui.add_space(10.0);
                         if project.is_indexing_docs {
@@ -373,6 +408,10 @@ impl App {
                             .fill(Color32::from_black_alpha(128))
                             .inner_margin(egui::Margin::same(4))
                             .show(ui, |ui| {
+                                if self.selected_project_view == ProjectView::ServerLog {
+                                    draw_server_log(ui, &project.server_log);
+                                    return;
+                                }
                                 ScrollArea::vertical()
                                     .auto_shrink([false, false])
                                     .show(ui, |ui| {
@@ -382,7 +421,9 @@ impl App {
                                             for event_tuple in project_events.iter().rev() {
                                                 if matches!(
                                                     event_tuple.1,
-                                                    ContextNotification::Lsp(_)
+                                                    ContextNotification::Lsp(
+                                                        LspNotification::Indexing { .. }
+                                                    )
                                                 ) {
                                                     continue;
                                                 }
@@ -582,6 +623,24 @@ impl<'a> ListCell<'a> {
         response
     }
 }
+
+fn draw_server_log(ui: &mut Ui, log: &ServerLog) {
+    let row_height = ui.text_style_height(&TextStyle::Monospace);
+    log.with_lines(|lines| {
+        // Only the visible lines are laid out, the log holds up to a thousand
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, lines.len(), |ui, rows| {
+                for line in lines.range(rows) {
+                    ui.monospace(line.to_string());
+                }
+            });
+    });
+    // New lines don't cause any event that would repaint the log
+    ui.ctx().request_repaint_after(Duration::from_secs(1));
+}
+
 fn find_root_project(mut path: &Path, projects: &[ProjectDescription]) -> Option<PathBuf> {
     if let Some(project) = projects.iter().find(|p| p.root == *path) {
         return Some(project.root.clone());
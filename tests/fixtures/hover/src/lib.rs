@@ -0,0 +1,256 @@
+/// Function 0
+pub fn function_0(value: u32) -> u32 {
+    value + 0
+}
+/// Function 1
+pub fn function_1(value: u32) -> u32 {
+    value + 1
+}
+/// Function 2
+pub fn function_2(value: u32) -> u32 {
+    value + 2
+}
+/// Function 3
+pub fn function_3(value: u32) -> u32 {
+    value + 3
+}
+/// Function 4
+pub fn function_4(value: u32) -> u32 {
+    value + 4
+}
+/// Function 5
+pub fn function_5(value: u32) -> u32 {
+    value + 5
+}
+/// Function 6
+pub fn function_6(value: u32) -> u32 {
+    value + 6
+}
+/// Function 7
+pub fn function_7(value: u32) -> u32 {
+    value + 7
+}
+/// Function 8
+pub fn function_8(value: u32) -> u32 {
+    value + 8
+}
+/// Function 9
+pub fn function_9(value: u32) -> u32 {
+    value + 9
+}
+/// Function 10
+pub fn function_10(value: u32) -> u32 {
+    value + 10
+}
+/// Function 11
+pub fn function_11(value: u32) -> u32 {
+    value + 11
+}
+/// Function 12
+pub fn function_12(value: u32) -> u32 {
+    value + 12
+}
+/// Function 13
+pub fn function_13(value: u32) -> u32 {
+    value + 13
+}
+/// Function 14
+pub fn function_14(value: u32) -> u32 {
+    value + 14
+}
+/// Function 15
+pub fn function_15(value: u32) -> u32 {
+    value + 15
+}
+/// Function 16
+pub fn function_16(value: u32) -> u32 {
+    value + 16
+}
+/// Function 17
+pub fn function_17(value: u32) -> u32 {
+    value + 17
+}
+/// Function 18
+pub fn function_18(value: u32) -> u32 {
+    value + 18
+}
+/// Function 19
+pub fn function_19(value: u32) -> u32 {
+    value + 19
+}
+/// Function 20
+pub fn function_20(value: u32) -> u32 {
+    value + 20
+}
+/// Function 21
+pub fn function_21(value: u32) -> u32 {
+    value + 21
+}
+/// Function 22
+pub fn function_22(value: u32) -> u32 {
+    value + 22
+}
+/// Function 23
+pub fn function_23(value: u32) -> u32 {
+    value + 23
+}
+/// Function 24
+pub fn function_24(value: u32) -> u32 {
+    value + 24
+}
+/// Function 25
+pub fn function_25(value: u32) -> u32 {
+    value + 25
+}
+/// Function 26
+pub fn function_26(value: u32) -> u32 {
+    value + 26
+}
+/// Function 27
+pub fn function_27(value: u32) -> u32 {
+    value + 27
+}
+/// Function 28
+pub fn function_28(value: u32) -> u32 {
+    value + 28
+}
+/// Function 29
+pub fn function_29(value: u32) -> u32 {
+    value + 29
+}
+/// Function 30
+pub fn function_30(value: u32) -> u32 {
+    value + 30
+}
+/// Function 31
+pub fn function_31(value: u32) -> u32 {
+    value + 31
+}
+/// Function 32
+pub fn function_32(value: u32) -> u32 {
+    value + 32
+}
+/// Function 33
+pub fn function_33(value: u32) -> u32 {
+    value + 33
+}
+/// Function 34
+pub fn function_34(value: u32) -> u32 {
+    value + 34
+}
+/// Function 35
+pub fn function_35(value: u32) -> u32 {
+    value + 35
+}
+/// Function 36
+pub fn function_36(value: u32) -> u32 {
+    value + 36
+}
+/// Function 37
+pub fn function_37(value: u32) -> u32 {
+    value + 37
+}
+/// Function 38
+pub fn function_38(value: u32) -> u32 {
+    value + 38
+}
+/// Function 39
+pub fn function_39(value: u32) -> u32 {
+    value + 39
+}
+/// Function 40
+pub fn function_40(value: u32) -> u32 {
+    value + 40
+}
+/// Function 41
+pub fn function_41(value: u32) -> u32 {
+    value + 41
+}
+/// Function 42
+pub fn function_42(value: u32) -> u32 {
+    value + 42
+}
+/// Function 43
+pub fn function_43(value: u32) -> u32 {
+    value + 43
+}
+/// Function 44
+pub fn function_44(value: u32) -> u32 {
+    value + 44
+}
+/// Function 45
+pub fn function_45(value: u32) -> u32 {
+    value + 45
+}
+/// Function 46
+pub fn function_46(value: u32) -> u32 {
+    value + 46
+}
+/// Function 47
+pub fn function_47(value: u32) -> u32 {
+    value + 47
+}
+/// Function 48
+pub fn function_48(value: u32) -> u32 {
+    value + 48
+}
+/// Function 49
+pub fn function_49(value: u32) -> u32 {
+    value + 49
+}
+/// Function 50
+pub fn function_50(value: u32) -> u32 {
+    value + 50
+}
+/// Function 51
+pub fn function_51(value: u32) -> u32 {
+    value + 51
+}
+/// Function 52
+pub fn function_52(value: u32) -> u32 {
+    value + 52
+}
+/// Function 53
+pub fn function_53(value: u32) -> u32 {
+    value + 53
+}
+/// Function 54
+pub fn function_54(value: u32) -> u32 {
+    value + 54
+}
+/// Function 55
+pub fn function_55(value: u32) -> u32 {
+    value + 55
+}
+/// Function 56
+pub fn function_56(value: u32) -> u32 {
+    value + 56
+}
+/// Function 57
+pub fn function_57(value: u32) -> u32 {
+    value + 57
+}
+/// Function 58
+pub fn function_58(value: u32) -> u32 {
+    value + 58
+}
+/// Function 59
+pub fn function_59(value: u32) -> u32 {
+    value + 59
+}
+/// Function 60
+pub fn function_60(value: u32) -> u32 {
+    value + 60
+}
+/// Function 61
+pub fn function_61(value: u32) -> u32 {
+    value + 61
+}
+/// Function 62
+pub fn function_62(value: u32) -> u32 {
+    value + 62
+}
+/// Function 63
+pub fn function_63(value: u32) -> u32 {
+    value + 63
+}